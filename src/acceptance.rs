@@ -0,0 +1,205 @@
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+
+/// The classic rule: always accept improvements, accept a worsening `delta` with
+/// probability `exp(-delta / t)`.
+pub fn metropolis(delta: f64, t: f64) -> f64 {
+    if delta < 0.0 {
+        1.0
+    } else {
+        f64::exp(-delta / t)
+    }
+}
+
+/// What an acceptance rule may look at when deciding on a move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AcceptanceContext {
+    pub delta: f64,
+    pub temperature: f64,
+    pub current_energy: f64,
+    pub best_energy: f64,
+    pub initial_energy: f64,
+    pub evaluations: usize,
+}
+
+/// Rule deciding the probability of accepting a proposed move.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Acceptance {
+    #[default]
+    Metropolis,
+    /// Barker (or Glauber) dynamics: `1 / (1 + exp(delta / t))`.
+    Barker,
+    /// Tsallis–Stariolo generalized annealing, which tends to Metropolis as `q` goes to 1.
+    Tsallis { q: f64 },
+    /// Deterministically accept any move worsening the energy by less than the
+    /// temperature.
+    Threshold,
+    /// Accept improvements and any move whose energy stays under a water level that
+    /// starts at the initial energy and drops by `rain_speed` per evaluation.
+    GreatDeluge { rain_speed: f64 },
+    /// Accept improvements and any move within `deviation` (relative) of the best energy.
+    RecordToRecord { deviation: f64 },
+}
+
+impl Acceptance {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            Acceptance::Tsallis { q } => ensure!(q.is_finite(), "q must be finite, got {q}"),
+            Acceptance::GreatDeluge { rain_speed } => ensure!(
+                rain_speed >= 0.0,
+                "rain speed must be non-negative, got {rain_speed}"
+            ),
+            Acceptance::RecordToRecord { deviation } => ensure!(
+                deviation >= 0.0,
+                "deviation must be non-negative, got {deviation}"
+            ),
+            Acceptance::Metropolis | Acceptance::Barker | Acceptance::Threshold => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn probability(&self, context: &AcceptanceContext) -> f64 {
+        let AcceptanceContext {
+            delta, temperature, ..
+        } = *context;
+        let new_energy = context.current_energy + delta;
+
+        match *self {
+            Acceptance::Metropolis => metropolis(delta, temperature),
+            Acceptance::Barker => 1.0 / (1.0 + f64::exp(delta / temperature)),
+            Acceptance::Tsallis { q } => {
+                if delta <= 0.0 {
+                    1.0
+                } else if (q - 1.0).abs() < 1e-12 {
+                    metropolis(delta, temperature)
+                } else {
+                    let base = 1.0 - (1.0 - q) * delta / temperature;
+
+                    if base > 0.0 {
+                        base.powf(1.0 / (1.0 - q)).min(1.0)
+                    } else {
+                        0.0
+                    }
+                }
+            }
+            Acceptance::Threshold => indicator(delta < temperature),
+            Acceptance::GreatDeluge { rain_speed } => {
+                let level = context.initial_energy - rain_speed * context.evaluations as f64;
+
+                indicator(delta <= 0.0 || new_energy <= level)
+            }
+            Acceptance::RecordToRecord { deviation } => {
+                let record = context.best_energy;
+
+                indicator(delta <= 0.0 || new_energy <= record + deviation * record.abs())
+            }
+        }
+    }
+}
+
+fn indicator(accept: bool) -> f64 {
+    if accept {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        schedule::{Geometric, StepsPerTemperature},
+        simulated_annealing::{Sampling, SimulatedAnnealing, State},
+        tsp::fixtures::shuffled_polygon,
+    };
+
+    use super::*;
+
+    fn context(delta: f64) -> AcceptanceContext {
+        AcceptanceContext {
+            delta,
+            temperature: 1.0,
+            current_energy: 10.0,
+            best_energy: 10.0,
+            initial_energy: 20.0,
+            evaluations: 0,
+        }
+    }
+
+    #[test]
+    fn test_probabilities() {
+        assert_eq!(Acceptance::Metropolis.probability(&context(-1.0)), 1.0);
+        assert_eq!(
+            Acceptance::Metropolis.probability(&context(1.0)),
+            f64::exp(-1.0)
+        );
+
+        assert_eq!(Acceptance::Barker.probability(&context(0.0)), 0.5);
+        assert!(Acceptance::Barker.probability(&context(-5.0)) > 0.99);
+
+        let tsallis = Acceptance::Tsallis { q: 1.0 + 1e-9 };
+        assert!((tsallis.probability(&context(1.0)) - f64::exp(-1.0)).abs() < 1e-6);
+        assert_eq!(
+            Acceptance::Tsallis { q: 0.5 }.probability(&context(3.0)),
+            0.0
+        );
+
+        assert_eq!(Acceptance::Threshold.probability(&context(0.5)), 1.0);
+        assert_eq!(Acceptance::Threshold.probability(&context(1.5)), 0.0);
+    }
+
+    #[test]
+    fn test_deterministic_rules() {
+        let deluge = Acceptance::GreatDeluge { rain_speed: 1.0 };
+
+        assert_eq!(deluge.probability(&context(5.0)), 1.0);
+        assert_eq!(
+            deluge.probability(&AcceptanceContext {
+                evaluations: 6,
+                ..context(5.0)
+            }),
+            0.0
+        );
+
+        let record = Acceptance::RecordToRecord { deviation: 0.1 };
+
+        assert_eq!(record.probability(&context(1.0)), 1.0);
+        assert_eq!(record.probability(&context(1.5)), 0.0);
+        assert_eq!(record.probability(&context(-1.0)), 1.0);
+    }
+
+    #[test]
+    fn test_acceptance_rules() {
+        let (tsp, best_energy) = shuffled_polygon(20);
+
+        let rules = [
+            Acceptance::Metropolis,
+            Acceptance::Barker,
+            Acceptance::Tsallis { q: 1.5 },
+            Acceptance::Threshold,
+            Acceptance::GreatDeluge { rain_speed: 3e-4 },
+            Acceptance::RecordToRecord { deviation: 0.02 },
+        ];
+
+        for acceptance in rules {
+            let report = SimulatedAnnealing::builder()
+                .with_acceptance(acceptance)
+                .with_sampling(Sampling::Metropolis)
+                .with_schedule(Geometric::new(0.95))
+                .with_steps_per_temperature(StepsPerTemperature::Proportional(2.0))
+                .with_max_iter(200)
+                .with_seed(0)
+                .build()
+                .run(&tsp);
+
+            assert!(report.best_energy < tsp.energy(), "{acceptance:?}");
+            assert!(
+                report.best_energy - best_energy < 0.5,
+                "{acceptance:?}: {}",
+                report.best_energy
+            );
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    matrix::{check_tour, DistanceMatrix},
+    simulated_annealing::State,
+    tsp::tour_length,
+};
+
+/// Moves proposed by an [`Atsp`]. None of them reverses a part of the tour, so their
+/// cost stays valid when `d(a, b) != d(b, a)`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectedNeighborhood {
+    /// Move a segment of one to three cities elsewhere in the tour.
+    #[default]
+    OrOpt,
+    /// Move a segment of any length elsewhere in the tour.
+    SegmentInsertion,
+    /// Cut the tour in three places and reconnect it without reversing any segment,
+    /// which exchanges two adjacent segments.
+    ThreeOpt,
+}
+
+/// Exchanges the adjacent segments `start..middle` and `middle..end` of the tour, with
+/// `start < middle < end <= n`. Moving a segment elsewhere is such an exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exchange {
+    pub start: usize,
+    pub middle: usize,
+    pub end: usize,
+}
+
+/// Asymmetric TSP over the nodes of a [`DistanceMatrix`], where `matrix.get(i, j)` is
+/// the cost of going from `i` to `j`.
+///
+/// As for a [`MatrixTsp`](crate::matrix::MatrixTsp), only an [`AtspTour`] is serialized
+/// and [`Atsp::from_tour`] attaches the matrix again.
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "AtspTour")]
+pub struct Atsp {
+    matrix: Arc<DistanceMatrix>,
+    tour: Vec<usize>,
+    neighborhood: DirectedNeighborhood,
+}
+
+impl Atsp {
+    /// Tour visiting the nodes in index order.
+    pub fn new(matrix: Arc<DistanceMatrix>) -> Self {
+        Self {
+            tour: (0..matrix.len()).collect(),
+            matrix,
+            neighborhood: DirectedNeighborhood::default(),
+        }
+    }
+
+    pub fn with_tour(mut self, tour: Vec<usize>) -> anyhow::Result<Self> {
+        check_tour(&tour, self.matrix.len())?;
+        self.tour = tour;
+
+        Ok(self)
+    }
+
+    pub fn with_neighborhood(mut self, neighborhood: DirectedNeighborhood) -> Self {
+        self.neighborhood = neighborhood;
+
+        self
+    }
+
+    pub fn tour(&self) -> &[usize] {
+        &self.tour
+    }
+
+    pub fn matrix(&self) -> &Arc<DistanceMatrix> {
+        &self.matrix
+    }
+
+    /// Tour saved from an `Atsp` over `matrix`.
+    pub fn from_tour(matrix: Arc<DistanceMatrix>, saved: AtspTour) -> anyhow::Result<Self> {
+        Self::new(matrix)
+            .with_neighborhood(saved.neighborhood)
+            .with_tour(saved.tour)
+    }
+
+    /// Moves `length` cities starting at a random position before or after a random
+    /// block of the rest of the tour. Tours of fewer than two cities have no moves and
+    /// are never asked for one.
+    fn propose_insertion<R: Rng + ?Sized>(&self, max_length: usize, rng: &mut R) -> Exchange {
+        let n = self.tour.len();
+
+        let length = rng.gen_range(1..=max_length.min(n - 1));
+        let i = rng.gen_range(0..=n - length);
+        let k = rng.gen_range(0..n - length);
+
+        if k < i {
+            Exchange {
+                start: k,
+                middle: i,
+                end: i + length,
+            }
+        } else {
+            Exchange {
+                start: i,
+                middle: i + length,
+                end: k + length + 1,
+            }
+        }
+    }
+}
+
+/// Wire format of an [`Atsp`], without its matrix.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtspTour {
+    pub tour: Vec<usize>,
+    pub neighborhood: DirectedNeighborhood,
+}
+
+impl From<Atsp> for AtspTour {
+    fn from(atsp: Atsp) -> Self {
+        Self {
+            tour: atsp.tour,
+            neighborhood: atsp.neighborhood,
+        }
+    }
+}
+
+impl State for Atsp {
+    type Move = Exchange;
+
+    fn energy(&self) -> f64 {
+        tour_length(&self.tour, |&a, &b| self.matrix.get(a, b))
+    }
+
+    fn propose<R: Rng + ?Sized>(&self, rng: &mut R) -> Exchange {
+        let n = self.tour.len();
+
+        match self.neighborhood {
+            DirectedNeighborhood::OrOpt => self.propose_insertion(3, rng),
+            DirectedNeighborhood::SegmentInsertion => self.propose_insertion(n - 1, rng),
+            DirectedNeighborhood::ThreeOpt => {
+                let mut cuts = rand::seq::index::sample(rng, n + 1, 3).into_vec();
+                cuts.sort_unstable();
+
+                Exchange {
+                    start: cuts[0],
+                    middle: cuts[1],
+                    end: cuts[2],
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, mv: &Exchange) {
+        self.tour[mv.start..mv.end].rotate_left(mv.middle - mv.start);
+    }
+
+    fn undo(&mut self, mv: &Exchange) {
+        self.tour[mv.start..mv.end].rotate_right(mv.middle - mv.start);
+    }
+
+    fn neighborhood_size(&self) -> usize {
+        let n = self.tour.len();
+
+        // Segments of `length` cities have `n - length + 1` starts and `n - length`
+        // destinations.
+        let insertions =
+            |max_length: usize| -> usize { (1..=max_length).map(|l| (n - l + 1) * (n - l)).sum() };
+
+        match self.neighborhood {
+            DirectedNeighborhood::OrOpt => insertions(3.min(n.saturating_sub(1))),
+            DirectedNeighborhood::SegmentInsertion => insertions(n.saturating_sub(1)),
+            DirectedNeighborhood::ThreeOpt => (n + 1) * n * n.saturating_sub(1) / 6,
+        }
+    }
+
+    fn delta_energy(&mut self, mv: &Exchange) -> f64 {
+        let n = self.tour.len();
+
+        // Rotating the whole tour keeps the same edges.
+        if mv.start == 0 && mv.end == n {
+            return 0.0;
+        }
+
+        let node = |position: usize| self.tour[position % n];
+        let edge = |a: usize, b: usize| self.matrix.get(node(a), node(b));
+
+        let before = mv.start + n - 1;
+
+        edge(before, mv.middle) + edge(mv.end - 1, mv.start) + edge(mv.middle - 1, mv.end)
+            - edge(before, mv.start)
+            - edge(mv.middle - 1, mv.middle)
+            - edge(mv.end - 1, mv.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        schedule::{Geometric, StepsPerTemperature},
+        simulated_annealing::{Sampling, SimulatedAnnealing},
+        stop::StopReason,
+    };
+
+    use super::*;
+
+    fn one_way_streets(n: usize) -> DistanceMatrix {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        DistanceMatrix::from_fn(n, |i, j| {
+            if i == j {
+                0.0
+            } else {
+                rng.gen_range(1..100) as f64
+            }
+        })
+    }
+
+    #[test]
+    fn test_delta_energy() {
+        let n = 7;
+        let mut atsp = Atsp::new(Arc::new(one_way_streets(n)));
+
+        let moves = (0..=n)
+            .tuple_combinations()
+            .map(|(start, middle, end)| Exchange { start, middle, end });
+
+        for mv in moves {
+            let before = atsp.energy();
+            let delta = atsp.delta_energy(&mv);
+
+            atsp.apply(&mv);
+            assert_eq!(atsp.energy() - before, delta, "{mv:?}");
+
+            atsp.undo(&mv);
+            assert_eq!(atsp.energy(), before);
+        }
+    }
+
+    #[test]
+    fn test_proposals_are_valid() {
+        let n = 9;
+        let atsp = Atsp::new(Arc::new(one_way_streets(n)));
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for neighborhood in [
+            DirectedNeighborhood::OrOpt,
+            DirectedNeighborhood::SegmentInsertion,
+            DirectedNeighborhood::ThreeOpt,
+        ] {
+            let atsp = atsp.clone().with_neighborhood(neighborhood);
+
+            for _ in 0..1000 {
+                let mv = atsp.propose(&mut rng);
+
+                assert!(mv.start < mv.middle && mv.middle < mv.end && mv.end <= n);
+
+                if neighborhood == DirectedNeighborhood::OrOpt {
+                    assert!(mv.middle - mv.start <= 3 || mv.end - mv.middle <= 3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_solves_asymmetric_instance() {
+        let n = 8;
+        let matrix = Arc::new(one_way_streets(n));
+
+        let optimum = (1..n)
+            .permutations(n - 1)
+            .map(|rest| {
+                let tour = std::iter::once(0).chain(rest).collect_vec();
+                tour_length(&tour, |&a, &b| matrix.get(a, b))
+            })
+            .fold(f64::INFINITY, f64::min);
+
+        for neighborhood in [
+            DirectedNeighborhood::OrOpt,
+            DirectedNeighborhood::SegmentInsertion,
+            DirectedNeighborhood::ThreeOpt,
+        ] {
+            let atsp = Atsp::new(matrix.clone()).with_neighborhood(neighborhood);
+
+            let report = SimulatedAnnealing::builder()
+                .with_sampling(Sampling::Metropolis)
+                .with_initial_temperature(50.0)
+                .with_schedule(Geometric::new(0.95))
+                .with_steps_per_temperature(StepsPerTemperature::Fixed(50))
+                .with_max_iter(200)
+                .with_seed(0)
+                .build()
+                .run(&atsp);
+
+            assert_eq!(report.best_energy, optimum, "{neighborhood:?}");
+        }
+    }
+
+    #[test]
+    fn test_tiny_instances_freeze() {
+        for n in [0, 1] {
+            for neighborhood in [
+                DirectedNeighborhood::OrOpt,
+                DirectedNeighborhood::SegmentInsertion,
+                DirectedNeighborhood::ThreeOpt,
+            ] {
+                let atsp = Atsp::new(Arc::new(one_way_streets(n))).with_neighborhood(neighborhood);
+
+                assert_eq!(atsp.neighborhood_size(), 0);
+
+                let report = SimulatedAnnealing::builder()
+                    .with_sampling(Sampling::Metropolis)
+                    .with_calibrated_initial_temperature(0.8, 10)
+                    .with_seed(0)
+                    .build()
+                    .run(&atsp);
+
+                assert_eq!(report.stop_reason, StopReason::Frozen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialized_without_matrix() {
+        let matrix = Arc::new(one_way_streets(5));
+        let atsp = Atsp::new(matrix.clone())
+            .with_neighborhood(DirectedNeighborhood::ThreeOpt)
+            .with_tour(vec![4, 2, 0, 1, 3])
+            .unwrap();
+
+        let json = serde_json::to_string(&atsp).unwrap();
+        assert_eq!(json, r#"{"tour":[4,2,0,1,3],"neighborhood":"three_opt"}"#);
+
+        let saved: AtspTour = serde_json::from_str(&json).unwrap();
+        let back = Atsp::from_tour(matrix.clone(), saved.clone()).unwrap();
+
+        assert_eq!(back.tour(), atsp.tour());
+        assert_eq!(back.energy(), atsp.energy());
+        assert!(Arc::ptr_eq(back.matrix(), &matrix));
+
+        assert!(Atsp::from_tour(Arc::new(one_way_streets(4)), saved).is_err());
+    }
+}
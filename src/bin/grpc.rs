@@ -7,13 +7,15 @@ pub mod grpc_sim_ann {
     tonic::include_proto!("grpc_sim_ann"); // The string specified here must match the proto package name
 }
 
-use itertools::Itertools;
 use rand::{
     distributions::{Distribution, Uniform},
-    seq::SliceRandom,
     SeedableRng,
 };
-use simulated_annealing::tsp2::{acceptability, Point, Tsp};
+use simulated_annealing::{
+    schedule::PowerLaw,
+    simulated_annealing::SimulatedAnnealing,
+    tsp::{Coordinates, Neighborhood, Point, Tsp},
+};
 
 fn generate_random(n_vertices: usize) -> Vec<Point> {
     let mut rng = rand::rngs::StdRng::from_entropy();
@@ -25,14 +27,6 @@ fn generate_random(n_vertices: usize) -> Vec<Point> {
         .collect()
 }
 
-fn geometric_swap<T>(points: &mut [T], i: usize, j: usize) {
-    let (i, j) = (i.min(j), i.max(j));
-
-    points.swap(i, j);
-
-    points[(i + 1)..j].reverse();
-}
-
 fn greedy(mut points: Vec<Point>) -> Vec<Point> {
     let mut new_order = Vec::new();
 
@@ -62,7 +56,7 @@ fn greedy(mut points: Vec<Point>) -> Vec<Point> {
     new_order
 }
 
-fn get_iterations() -> Vec<Tsp> {
+fn get_iterations() -> Vec<Coordinates> {
     let n_vertices: usize = std::env::var("N_VERTICES")
         .map(|x| {
             x.parse()
@@ -70,43 +64,23 @@ fn get_iterations() -> Vec<Tsp> {
         })
         .unwrap_or(40);
 
-    let mut state = generate_random(n_vertices);
+    let state = greedy(generate_random(n_vertices));
+    let tsp = Tsp::new(state).with_neighborhood(Neighborhood::TwoOpt);
 
-    let mut rng = rand::rngs::StdRng::from_entropy();
-
-    state = greedy(state);
-
-    let uniform = Uniform::new_inclusive(0.0, 1.0);
+    let sa = SimulatedAnnealing::builder()
+        .with_schedule(PowerLaw::new(1.2))
+        .with_initial_temperature(10.0)
+        .with_max_iter(5000)
+        .build();
 
-    let current_state = &mut state.to_vec();
-    let n = current_state.len();
-
-    let mut holder = vec![Point::default(); n];
-    let mut tuple_combs = (1..n).tuple_combinations::<(usize, usize)>().collect_vec();
-    tuple_combs.retain(|tup| *tup != (1, n - 1));
+    let mut steps = sa.iter(&tsp);
 
     let mut results = Vec::new();
 
-    'outer: for k in 0..5000 {
-        let t = 10.0 / (k as f64).powf(1.2);
-
-        tuple_combs.shuffle(&mut rng);
-
-        for (i, j) in tuple_combs.iter() {
-            holder.copy_from_slice(current_state);
-            geometric_swap(&mut holder, *i, *j);
-
-            if acceptability(current_state, &holder, t) >= uniform.sample(&mut rng) {
-                current_state.copy_from_slice(&holder);
-
-                let (x, y) = current_state.iter().map(|p| (p.0, p.1)).unzip();
-
-                results.push(Tsp { x, y });
-                continue 'outer;
-            }
+    while let Some(step) = steps.next() {
+        if step.accepted {
+            results.push(Coordinates::from(steps.state()));
         }
-
-        break;
     }
 
     results
@@ -121,12 +95,17 @@ impl DataPointsProvider for MyProvider {
         &self,
         _: Request<Empty>, // Accept request of type HelloRequest
     ) -> Result<Response<DataPoints>, Status> {
-        let iterations = get_iterations();
+        let iterations = tokio::task::spawn_blocking(get_iterations)
+            .await
+            .map_err(|error| Status::internal(error.to_string()))?;
 
         let data = iterations
             .into_iter()
-            .map(|tsp| DataPoint { x: tsp.x, y: tsp.y })
-            .collect_vec();
+            .map(|coordinates| DataPoint {
+                x: coordinates.x,
+                y: coordinates.y,
+            })
+            .collect();
 
         let data_points = DataPoints { data };
 
@@ -1,20 +1,27 @@
 use std::time::Duration;
 
 use axum::{
-    extract::{ws::WebSocket, WebSocketUpgrade},
+    extract::{
+        ws::{Message, WebSocket},
+        WebSocketUpgrade,
+    },
     response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
-use itertools::Itertools;
 use rand::{
     distributions::{Distribution, Uniform},
-    seq::SliceRandom,
     SeedableRng,
 };
 use serde_json::json;
-use simulated_annealing::tsp2::{acceptability, Point, Tsp};
-use tokio::time::sleep;
+use simulated_annealing::{
+    schedule::PowerLaw,
+    simulated_annealing::SimulatedAnnealing,
+    stream::StreamOptions,
+    tsp::{Coordinates, Neighborhood, Point, Tsp},
+};
+use tokio::time::{interval, MissedTickBehavior};
+use tokio_stream::StreamExt;
 
 fn _generate_circle(n_vertices: usize) -> Vec<Point> {
     let z = num::Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / n_vertices as f64);
@@ -35,14 +42,6 @@ fn generate_random(n_vertices: usize) -> Vec<Point> {
         .collect()
 }
 
-fn geometric_swap<T>(points: &mut [T], i: usize, j: usize) {
-    let (i, j) = (i.min(j), i.max(j));
-
-    points.swap(i, j);
-
-    points[(i + 1)..j].reverse();
-}
-
 fn greedy(mut points: Vec<Point>) -> Vec<Point> {
     let mut new_order = Vec::new();
 
@@ -76,7 +75,7 @@ async fn handler(ws: WebSocketUpgrade) -> Response {
     ws.on_upgrade(handle_socket)
 }
 
-async fn get_iterations() -> impl IntoResponse {
+fn initial_tour() -> Tsp {
     let n_vertices: usize = std::env::var("N_VERTICES")
         .map(|x| {
             x.parse()
@@ -84,98 +83,60 @@ async fn get_iterations() -> impl IntoResponse {
         })
         .unwrap_or(40);
 
-    let mut state = generate_random(n_vertices);
+    let state = greedy(generate_random(n_vertices));
 
-    let mut rng = rand::rngs::StdRng::from_entropy();
-
-    state = greedy(state);
-
-    let uniform = Uniform::new_inclusive(0.0, 1.0);
+    Tsp::new(state).with_neighborhood(Neighborhood::TwoOpt)
+}
 
-    let current_state = &mut state.to_vec();
-    let n = current_state.len();
+fn solver() -> SimulatedAnnealing {
+    SimulatedAnnealing::builder()
+        .with_schedule(PowerLaw::new(1.2))
+        .with_initial_temperature(10.0)
+        .with_max_iter(5000)
+        .build()
+}
 
-    let mut holder = vec![Point::default(); n];
-    let mut tuple_combs = (1..n).tuple_combinations::<(usize, usize)>().collect_vec();
-    tuple_combs.retain(|tup| *tup != (1, n - 1));
+fn iterations() -> Vec<Coordinates> {
+    let sa = solver();
+    let mut steps = sa.iter(&initial_tour());
 
     let mut results = Vec::new();
 
-    'outer: for k in 0..5000 {
-        let t = 10.0 / (k as f64).powf(1.2);
-
-        tuple_combs.shuffle(&mut rng);
-
-        for (i, j) in tuple_combs.iter() {
-            holder.copy_from_slice(current_state);
-            geometric_swap(&mut holder, *i, *j);
-
-            if acceptability(current_state, &holder, t) >= uniform.sample(&mut rng) {
-                current_state.copy_from_slice(&holder);
-
-                let (x, y) = current_state.iter().map(|p| (p.0, p.1)).unzip();
-
-                results.push(Tsp { x, y });
-                continue 'outer;
-            }
+    while let Some(step) = steps.next() {
+        if step.accepted {
+            results.push(Coordinates::from(steps.state()));
         }
-
-        break;
     }
 
-    Json(results)
+    results
 }
 
-async fn handle_socket(mut socket: WebSocket) {
-    let n_vertices: usize = std::env::var("N_VERTICES")
-        .map(|x| {
-            x.parse()
-                .expect("The env variable 'N_VERTICES' should be convertible to integer.")
-        })
-        .unwrap_or(40);
-
-    let mut state = generate_random(n_vertices);
-
-    let mut rng = rand::rngs::StdRng::from_entropy();
-
-    state = greedy(state);
-
-    let uniform = Uniform::new_inclusive(0.0, 1.0);
-
-    let current_state = &mut state.to_vec();
-    let n = current_state.len();
-
-    let mut holder = vec![Point::default(); n];
-    let mut tuple_combs = (1..n).tuple_combinations::<(usize, usize)>().collect_vec();
-    tuple_combs.retain(|tup| *tup != (1, n - 1));
-
-    'outer: for k in 0..5000 {
-        let t = 10.0 / (k as f64).powf(1.2);
-
-        tuple_combs.shuffle(&mut rng);
+async fn get_iterations() -> impl IntoResponse {
+    let results = tokio::task::spawn_blocking(iterations).await.unwrap();
 
-        for (i, j) in tuple_combs.iter() {
-            holder.copy_from_slice(current_state);
-            geometric_swap(&mut holder, *i, *j);
+    Json(results)
+}
 
-            if acceptability(current_state, &holder, t) >= uniform.sample(&mut rng) {
-                current_state.copy_from_slice(&holder);
+async fn handle_socket(mut socket: WebSocket) {
+    // Every accepted tour is a frame. The annealing thread waits on the full channel, so
+    // pacing the sends here paces the whole run.
+    let mut snapshots = solver().into_stream(initial_tour(), StreamOptions::new());
 
-                let (x, y) = current_state.iter().map(|p| (p.0, p.1)).unzip();
+    let mut frames = interval(Duration::from_millis(10));
+    frames.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
-                let jsonified = json!(Tsp { x, y });
+    while let Some(snapshot) = snapshots.next().await {
+        frames.tick().await;
 
-                socket
-                    .send(axum::extract::ws::Message::Text(jsonified.to_string()))
-                    .await
-                    .unwrap();
+        let jsonified = json!(Coordinates::from(&snapshot.state));
 
-                sleep(Duration::from_millis(10)).await;
-                continue 'outer;
-            }
+        if socket
+            .send(Message::Text(jsonified.to_string()))
+            .await
+            .is_err()
+        {
+            break;
         }
-
-        break;
     }
 }
 
@@ -193,25 +154,10 @@ async fn main() {
 
 #[cfg(test)]
 mod tests {
-    use simulated_annealing::tsp2::energy;
+    use simulated_annealing::simulated_annealing::State;
 
     use super::*;
 
-    #[test]
-    fn test_geometric_swap() {
-        let mut points = vec![0, 1, 2, 3, 4, 0, 1, 2, 10, 11, 12, 13, 14];
-
-        geometric_swap(&mut points, 5, 8);
-
-        assert_eq!(points, vec![0, 1, 2, 3, 4, 10, 2, 1, 0, 11, 12, 13, 14]);
-
-        let mut points = vec![0, 1, 2, 3, 4, 0, 1, 2, 3, 4, 5, 6, 10, 11, 12];
-
-        geometric_swap(&mut points, 5, 12);
-
-        assert_eq!(points, vec![0, 1, 2, 3, 4, 10, 6, 5, 4, 3, 2, 1, 0, 11, 12]);
-    }
-
     #[test]
     fn test_greedy() {
         let correct_order = vec![
@@ -238,6 +184,6 @@ mod tests {
 
         let ordered = greedy(unordered);
 
-        assert_eq!(energy(&ordered), energy(&correct_order));
+        assert_eq!(Tsp::new(ordered).energy(), Tsp::new(correct_order).energy());
     }
 }
@@ -1,15 +1,44 @@
-use crate::simulated_annealing::SimulatedAnnealing;
+use std::sync::Arc;
 
-pub struct SimulatedAnnealingBuilder {
-    temperature: Box<dyn Fn(usize) -> f64>,
-    max_k: usize,
+use crate::{
+    acceptance::Acceptance,
+    schedule::{
+        AdaptiveCooling, CoolingSchedule, InitialTemperature, Linear, Reheating,
+        StepsPerTemperature,
+    },
+    simulated_annealing::{Sampling, SimulatedAnnealing},
+    stop::StopCondition,
+};
+
+#[derive(Clone)]
+pub struct SimulatedAnnealingBuilder<O = ()> {
+    pub(crate) schedule: Arc<dyn CoolingSchedule + Send + Sync>,
+    pub(crate) initial_temperature: InitialTemperature,
+    pub(crate) adaptive_cooling: Option<AdaptiveCooling>,
+    pub(crate) reheating: Option<Reheating>,
+    pub(crate) steps_per_temperature: StepsPerTemperature,
+    pub(crate) sampling: Sampling,
+    pub(crate) acceptance: Acceptance,
+    pub(crate) max_k: usize,
+    pub(crate) seed: Option<u64>,
+    pub(crate) stop_conditions: Vec<StopCondition>,
+    pub(crate) observer: O,
 }
 
 impl Default for SimulatedAnnealingBuilder {
     fn default() -> Self {
         SimulatedAnnealingBuilder {
-            temperature: Box::new(|k| 1.0 - 0.01 * k as f64),
+            schedule: Arc::new(Linear::new(0.01, 1e-3)),
+            initial_temperature: InitialTemperature::Fixed(1.0),
+            adaptive_cooling: None,
+            reheating: None,
+            steps_per_temperature: StepsPerTemperature::Fixed(1),
+            sampling: Sampling::default(),
+            acceptance: Acceptance::default(),
             max_k: 100,
+            seed: None,
+            stop_conditions: Vec::new(),
+            observer: (),
         }
     }
 }
@@ -18,21 +47,139 @@ impl SimulatedAnnealingBuilder {
     pub fn new() -> SimulatedAnnealingBuilder {
         SimulatedAnnealingBuilder::default()
     }
+}
 
+impl<O> SimulatedAnnealingBuilder<O> {
     pub fn with_temperature_and_max_iter<T>(mut self, temperature: T, max_k: usize) -> Self
     where
-        T: Fn(usize) -> f64 + 'static,
+        T: Fn(usize) -> f64 + Send + Sync + 'static,
     {
-        self.temperature = Box::new(temperature);
+        self.schedule = Arc::new(temperature);
         self.max_k = max_k;
 
         self
     }
 
-    pub fn build(self) -> SimulatedAnnealing {
+    pub fn with_schedule<C>(mut self, schedule: C) -> Self
+    where
+        C: CoolingSchedule + Send + Sync + 'static,
+    {
+        self.schedule = Arc::new(schedule);
+
+        self
+    }
+
+    pub fn with_initial_temperature(mut self, t0: f64) -> Self {
+        assert!(t0 > 0.0, "initial temperature must be positive, got {t0}");
+        self.initial_temperature = InitialTemperature::Fixed(t0);
+
+        self
+    }
+
+    /// Picks the initial temperature at the start of each run so that a fraction
+    /// `acceptance` of the uphill moves among `samples` random moves from the initial
+    /// state would be accepted.
+    pub fn with_calibrated_initial_temperature(mut self, acceptance: f64, samples: usize) -> Self {
+        assert!(
+            0.0 < acceptance && acceptance < 1.0,
+            "acceptance must be in (0, 1), got {acceptance}"
+        );
+        self.initial_temperature = InitialTemperature::Calibrated {
+            acceptance,
+            samples,
+        };
+
+        self
+    }
+
+    /// Drives the temperature from the measured acceptance ratio instead of the
+    /// cooling schedule.
+    pub fn with_adaptive_cooling(mut self, adaptive_cooling: AdaptiveCooling) -> Self {
+        self.adaptive_cooling = Some(adaptive_cooling);
+
+        self
+    }
+
+    pub fn with_reheating(mut self, reheating: Reheating) -> Self {
+        self.reheating = Some(reheating);
+
+        self
+    }
+
+    /// Number of steps made at each temperature before cooling.
+    pub fn with_steps_per_temperature(mut self, steps: StepsPerTemperature) -> Self {
+        self.steps_per_temperature = steps;
+
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+
+        self
+    }
+
+    /// Rule deciding whether a proposed move is taken. Calibration of the initial
+    /// temperature always assumes [`Acceptance::Metropolis`].
+    pub fn with_acceptance(mut self, acceptance: Acceptance) -> Self {
+        if let Err(error) = acceptance.validate() {
+            panic!("{error}");
+        }
+        self.acceptance = acceptance;
+
+        self
+    }
+
+    pub fn with_max_iter(mut self, max_k: usize) -> Self {
+        self.max_k = max_k;
+
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+
+        self
+    }
+
+    /// Adds a condition that ends the run early. Conditions are combined with "or".
+    pub fn with_stop_condition(mut self, condition: StopCondition) -> Self {
+        self.stop_conditions.push(condition);
+
+        self
+    }
+
+    /// Registers the [`Observer`](crate::observer::Observer) notified during runs,
+    /// replacing any previous one.
+    pub fn with_observer<P>(self, observer: P) -> SimulatedAnnealingBuilder<P> {
+        SimulatedAnnealingBuilder {
+            schedule: self.schedule,
+            initial_temperature: self.initial_temperature,
+            adaptive_cooling: self.adaptive_cooling,
+            reheating: self.reheating,
+            steps_per_temperature: self.steps_per_temperature,
+            sampling: self.sampling,
+            acceptance: self.acceptance,
+            max_k: self.max_k,
+            seed: self.seed,
+            stop_conditions: self.stop_conditions,
+            observer,
+        }
+    }
+
+    pub fn build(self) -> SimulatedAnnealing<O> {
         SimulatedAnnealing {
-            temperature: self.temperature,
+            schedule: self.schedule,
+            initial_temperature: self.initial_temperature,
+            adaptive_cooling: self.adaptive_cooling,
+            reheating: self.reheating,
+            steps_per_temperature: self.steps_per_temperature,
+            sampling: self.sampling,
+            acceptance: self.acceptance,
             max_k: self.max_k,
+            seed: self.seed,
+            stop_conditions: self.stop_conditions,
+            observer: self.observer,
         }
     }
 }
@@ -0,0 +1,324 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{ensure, Context};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    observer::Observer,
+    simulated_annealing::{RunReport, SimulatedAnnealing, State},
+    steps::Steps,
+    stop::StopReason,
+};
+
+/// Everything needed to continue a run exactly where it was left, including the
+/// position of the RNG stream.
+///
+/// The solver configuration is not part of the checkpoint: resume with the same
+/// [`SimulatedAnnealing`] to get the same result as an uninterrupted run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint<S> {
+    pub(crate) current_state: S,
+    pub(crate) current_energy: f64,
+    pub(crate) best_state: S,
+    pub(crate) best_energy: f64,
+    pub(crate) initial_energy: f64,
+
+    pub(crate) t0: f64,
+    pub(crate) t: f64,
+    pub(crate) t_start: f64,
+    pub(crate) last_reheat: usize,
+    pub(crate) reheats: usize,
+
+    pub(crate) k: usize,
+    pub(crate) step: usize,
+    pub(crate) steps: usize,
+    pub(crate) attempts: usize,
+    pub(crate) improved: bool,
+    pub(crate) window_start: (usize, usize),
+
+    pub(crate) iterations: usize,
+    pub(crate) evaluations: usize,
+    pub(crate) accepted: usize,
+    pub(crate) stagnation: usize,
+    pub(crate) stop_reason: Option<StopReason>,
+    pub(crate) elapsed: Duration,
+
+    pub(crate) rng: ChaCha8Rng,
+}
+
+impl<S> Checkpoint<S> {
+    pub fn state(&self) -> &S {
+        &self.current_state
+    }
+
+    pub fn best_state(&self) -> &S {
+        &self.best_state
+    }
+
+    pub fn best_energy(&self) -> f64 {
+        self.best_energy
+    }
+
+    pub fn iteration(&self) -> usize {
+        self.k
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.t
+    }
+
+    /// Converts both saved states, e.g. to attach again the distance matrix left out of
+    /// a serialized [`MatrixTsp`](crate::matrix::MatrixTsp).
+    pub fn try_map<T, F>(self, mut f: F) -> anyhow::Result<Checkpoint<T>>
+    where
+        F: FnMut(S) -> anyhow::Result<T>,
+    {
+        Ok(Checkpoint {
+            current_state: f(self.current_state)?,
+            current_energy: self.current_energy,
+            best_state: f(self.best_state)?,
+            best_energy: self.best_energy,
+            initial_energy: self.initial_energy,
+            t0: self.t0,
+            t: self.t,
+            t_start: self.t_start,
+            last_reheat: self.last_reheat,
+            reheats: self.reheats,
+            k: self.k,
+            step: self.step,
+            steps: self.steps,
+            attempts: self.attempts,
+            improved: self.improved,
+            window_start: self.window_start,
+            iterations: self.iterations,
+            evaluations: self.evaluations,
+            accepted: self.accepted,
+            stagnation: self.stagnation,
+            stop_reason: self.stop_reason,
+            elapsed: self.elapsed,
+            rng: self.rng,
+        })
+    }
+
+    /// Writes the checkpoint as JSON. The file is replaced atomically, so a crash while
+    /// saving leaves the previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()>
+    where
+        S: Serialize,
+    {
+        let path = path.as_ref();
+
+        let mut tmp_name = path
+            .file_name()
+            .with_context(|| format!("{} is not a file path", path.display()))?
+            .to_os_string();
+        tmp_name.push(".tmp");
+        let tmp = path.with_file_name(tmp_name);
+
+        let file = File::create(&tmp)
+            .with_context(|| format!("could not create checkpoint {}", tmp.display()))?;
+        let mut writer = BufWriter::new(file);
+
+        serde_json::to_writer(&mut writer, self).context("could not serialize checkpoint")?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+
+        fs::rename(&tmp, path)
+            .with_context(|| format!("could not write checkpoint {}", path.display()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self>
+    where
+        S: DeserializeOwned,
+    {
+        let path = path.as_ref();
+
+        let file = File::open(path)
+            .with_context(|| format!("could not open checkpoint {}", path.display()))?;
+
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("invalid checkpoint {}", path.display()))
+    }
+}
+
+impl<O> SimulatedAnnealing<O> {
+    /// Continues the run saved in `checkpoint`. With the same configuration, the steps
+    /// are those the original run would have made.
+    pub fn resume<S>(&self, checkpoint: Checkpoint<S>) -> Steps<'_, S, O, ChaCha8Rng>
+    where
+        S: State + Clone,
+        O: Observer<S>,
+    {
+        Steps::from_checkpoint(self, checkpoint)
+    }
+
+    /// Runs from `state` like [`SimulatedAnnealing::run`], saving a checkpoint to `path`
+    /// every `every` temperature levels.
+    pub fn run_with_checkpoints<S>(
+        &self,
+        state: &S,
+        path: impl AsRef<Path>,
+        every: usize,
+    ) -> anyhow::Result<RunReport<S>>
+    where
+        S: State + Clone + Serialize,
+        O: Observer<S>,
+    {
+        checkpointed(self.iter(state), path.as_ref(), every)
+    }
+
+    /// Resumes from the checkpoint at `path` and keeps saving to it every `every`
+    /// temperature levels.
+    pub fn resume_from_file<S>(
+        &self,
+        path: impl AsRef<Path>,
+        every: usize,
+    ) -> anyhow::Result<RunReport<S>>
+    where
+        S: State + Clone + Serialize + DeserializeOwned,
+        O: Observer<S>,
+    {
+        let path = path.as_ref();
+        let checkpoint = Checkpoint::load(path)?;
+
+        self.resume_with_checkpoints(checkpoint, path, every)
+    }
+
+    /// Continues `checkpoint` like [`SimulatedAnnealing::resume`], saving to `path` every
+    /// `every` temperature levels. Use it for states that cannot be deserialized alone.
+    pub fn resume_with_checkpoints<S>(
+        &self,
+        checkpoint: Checkpoint<S>,
+        path: impl AsRef<Path>,
+        every: usize,
+    ) -> anyhow::Result<RunReport<S>>
+    where
+        S: State + Clone + Serialize,
+        O: Observer<S>,
+    {
+        checkpointed(self.resume(checkpoint), path.as_ref(), every)
+    }
+}
+
+fn checkpointed<S, O>(
+    mut steps: Steps<'_, S, O, ChaCha8Rng>,
+    path: &Path,
+    every: usize,
+) -> anyhow::Result<RunReport<S>>
+where
+    S: State + Clone + Serialize,
+    O: Observer<S>,
+{
+    ensure!(every > 0, "checkpoint interval must be positive");
+
+    let mut saved = steps.iterations();
+
+    while steps.next().is_some() {
+        if steps.iterations() >= saved + every {
+            steps.checkpoint().save(path)?;
+            saved = steps.iterations();
+        }
+    }
+
+    Ok(steps.into_report())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        schedule::{Geometric, StepsPerTemperature},
+        simulated_annealing::Sampling,
+        stop::StopCondition,
+        tsp::{fixtures::grid, Point, Tsp},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_save_next_to_target() {
+        let sa = SimulatedAnnealing::builder()
+            .with_max_iter(10)
+            .with_seed(0)
+            .build();
+
+        let tsp = Tsp::new(vec![Point(0.0, 0.0), Point(1.0, 0.0), Point(0.0, 1.0)]);
+        let checkpoint = sa.iter(&tsp).checkpoint();
+
+        let dir = std::env::temp_dir().join(format!("checkpoint-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for name in ["run.json", "run.tmp"] {
+            let path = dir.join(name);
+
+            checkpoint.save(&path).unwrap();
+
+            assert_eq!(Checkpoint::<Tsp>::load(&path).unwrap().iteration(), 0);
+            assert!(!dir.join(format!("{name}.tmp")).exists());
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let tsp = Tsp::new(grid());
+
+        let sa = SimulatedAnnealing::builder()
+            .with_sampling(Sampling::Metropolis)
+            .with_schedule(Geometric::new(0.95))
+            .with_steps_per_temperature(StepsPerTemperature::Fixed(50))
+            .with_max_iter(200)
+            .with_seed(0)
+            .build();
+
+        let report = sa.run(&tsp);
+
+        let mut steps = sa.iter(&tsp);
+        steps.by_ref().take(1234).for_each(drop);
+
+        let json = serde_json::to_string(&steps.checkpoint()).unwrap();
+        let checkpoint: Checkpoint<Tsp> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(checkpoint.iteration(), 24);
+
+        let mut resumed = sa.resume(checkpoint);
+        resumed.by_ref().for_each(drop);
+        let resumed = resumed.into_report();
+
+        assert_eq!(resumed.best_state.points(), report.best_state.points());
+        assert_eq!(resumed.final_state.points(), report.final_state.points());
+        assert_eq!(resumed.evaluations, report.evaluations);
+        assert_eq!(resumed.accepted, report.accepted);
+        assert_eq!(resumed.stop_reason, StopReason::MaxIterations);
+
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.json", std::process::id()));
+
+        let first = SimulatedAnnealing::builder()
+            .with_sampling(Sampling::Metropolis)
+            .with_schedule(Geometric::new(0.95))
+            .with_steps_per_temperature(StepsPerTemperature::Fixed(50))
+            .with_max_iter(200)
+            .with_seed(0)
+            .with_stop_condition(StopCondition::MaxEvaluations(5000))
+            .build()
+            .run_with_checkpoints(&tsp, &path, 10)
+            .unwrap();
+
+        assert_eq!(first.stop_reason, StopReason::MaxEvaluations);
+        assert_eq!(Checkpoint::<Tsp>::load(&path).unwrap().iteration(), 100);
+
+        let resumed = sa.resume_from_file::<Tsp>(&path, 10).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(resumed.best_state.points(), report.best_state.points());
+        assert_eq!(resumed.evaluations, report.evaluations);
+        assert!(sa.resume_from_file::<Tsp>(&path, 10).is_err());
+        assert!(sa.run_with_checkpoints(&tsp, &path, 0).is_err());
+    }
+}
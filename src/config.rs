@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    acceptance::Acceptance,
+    builder::SimulatedAnnealingBuilder,
+    schedule::{
+        AcceptanceTarget, AdaptiveCooling, CoolingSchedule, ExponentialWithFloor, Geometric,
+        InitialTemperature, Linear, Logarithmic, LundyMees, PowerLaw, Reheating,
+        StepsPerTemperature,
+    },
+    simulated_annealing::Sampling,
+    stop::StopCondition,
+};
+
+/// Declarative counterpart of the built-in cooling schedules.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleConfig {
+    Linear { rate: f64, floor: f64 },
+    Geometric { alpha: f64 },
+    Logarithmic,
+    LundyMees { beta: f64 },
+    ExponentialWithFloor { rate: f64, floor: f64 },
+    PowerLaw { exponent: f64 },
+}
+
+impl ScheduleConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            ScheduleConfig::Linear { rate, floor }
+            | ScheduleConfig::ExponentialWithFloor { rate, floor } => {
+                ensure!(rate > 0.0, "rate must be positive, got {rate}");
+                ensure!(floor > 0.0, "floor must be positive, got {floor}");
+            }
+            ScheduleConfig::Geometric { alpha } => {
+                ensure!(
+                    0.0 < alpha && alpha < 1.0,
+                    "alpha must be in (0, 1), got {alpha}"
+                );
+            }
+            ScheduleConfig::Logarithmic => {}
+            ScheduleConfig::LundyMees { beta } => {
+                ensure!(beta > 0.0, "beta must be positive, got {beta}");
+            }
+            ScheduleConfig::PowerLaw { exponent } => {
+                ensure!(exponent > 0.0, "exponent must be positive, got {exponent}");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn into_schedule(self) -> Arc<dyn CoolingSchedule + Send + Sync> {
+        match self {
+            ScheduleConfig::Linear { rate, floor } => Arc::new(Linear::new(rate, floor)),
+            ScheduleConfig::Geometric { alpha } => Arc::new(Geometric::new(alpha)),
+            ScheduleConfig::Logarithmic => Arc::new(Logarithmic),
+            ScheduleConfig::LundyMees { beta } => Arc::new(LundyMees::new(beta)),
+            ScheduleConfig::ExponentialWithFloor { rate, floor } => {
+                Arc::new(ExponentialWithFloor::new(rate, floor))
+            }
+            ScheduleConfig::PowerLaw { exponent } => Arc::new(PowerLaw::new(exponent)),
+        }
+    }
+}
+
+/// Solver settings that can be stored as JSON or TOML and turned into a
+/// [`SimulatedAnnealingBuilder`]. Missing fields take the builder defaults, including
+/// those nested in `adaptive_cooling` and `reheating`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverConfig {
+    pub schedule: ScheduleConfig,
+    pub initial_temperature: InitialTemperature,
+    pub adaptive_cooling: Option<AdaptiveCooling>,
+    pub reheating: Option<Reheating>,
+    pub steps_per_temperature: StepsPerTemperature,
+    pub sampling: Sampling,
+    pub acceptance: Acceptance,
+    pub max_iterations: usize,
+    pub seed: Option<u64>,
+    pub stop_conditions: Vec<StopCondition>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            schedule: ScheduleConfig::Linear {
+                rate: 0.01,
+                floor: 1e-3,
+            },
+            initial_temperature: InitialTemperature::Fixed(1.0),
+            adaptive_cooling: None,
+            reheating: None,
+            steps_per_temperature: StepsPerTemperature::Fixed(1),
+            sampling: Sampling::default(),
+            acceptance: Acceptance::default(),
+            max_iterations: 100,
+            seed: None,
+            stop_conditions: Vec::new(),
+        }
+    }
+}
+
+impl SolverConfig {
+    /// Checks the values the builder would otherwise reject by panicking.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.schedule.validate().context("invalid schedule")?;
+        self.acceptance.validate().context("invalid acceptance")?;
+
+        match self.initial_temperature {
+            InitialTemperature::Fixed(t0) => {
+                ensure!(t0 > 0.0, "initial temperature must be positive, got {t0}");
+            }
+            InitialTemperature::Calibrated { acceptance, .. } => {
+                ensure!(
+                    0.0 < acceptance && acceptance < 1.0,
+                    "acceptance must be in (0, 1), got {acceptance}"
+                );
+            }
+        }
+
+        if let Some(adaptive) = &self.adaptive_cooling {
+            ensure!(adaptive.window > 0, "adaptive window must be positive");
+            ensure!(adaptive.gain > 0.0, "adaptive gain must be positive");
+
+            if let AcceptanceTarget::Geometric { initial, last } = adaptive.target {
+                ensure!(
+                    initial > 0.0 && last > 0.0,
+                    "acceptance targets must be positive"
+                );
+            }
+        }
+
+        if let Some(reheating) = &self.reheating {
+            ensure!(reheating.stagnation > 0, "stagnation must be positive");
+            ensure!(reheating.fraction > 0.0, "reheat fraction must be positive");
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<SolverConfig> for SimulatedAnnealingBuilder {
+    type Error = anyhow::Error;
+
+    fn try_from(config: SolverConfig) -> anyhow::Result<Self> {
+        config.validate()?;
+
+        Ok(SimulatedAnnealingBuilder {
+            schedule: config.schedule.into_schedule(),
+            initial_temperature: config.initial_temperature,
+            adaptive_cooling: config.adaptive_cooling,
+            reheating: config.reheating,
+            steps_per_temperature: config.steps_per_temperature,
+            sampling: config.sampling,
+            acceptance: config.acceptance,
+            max_k: config.max_iterations,
+            seed: config.seed,
+            stop_conditions: config.stop_conditions,
+            observer: (),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let config = SolverConfig {
+            schedule: ScheduleConfig::PowerLaw { exponent: 1.2 },
+            initial_temperature: InitialTemperature::Calibrated {
+                acceptance: 0.8,
+                samples: 500,
+            },
+            adaptive_cooling: Some(AdaptiveCooling::new(AcceptanceTarget::LamDelosme, 100)),
+            reheating: Some(Reheating::new(50).restarting_from_best()),
+            steps_per_temperature: StepsPerTemperature::Proportional(0.5),
+            sampling: Sampling::Metropolis,
+            acceptance: Acceptance::Tsallis { q: 1.5 },
+            max_iterations: 5000,
+            seed: Some(42),
+            stop_conditions: vec![
+                StopCondition::TimeLimit(Duration::from_secs(3)),
+                StopCondition::TargetEnergy(10.0),
+            ],
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed: SolverConfig = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        assert!(SimulatedAnnealingBuilder::try_from(parsed).is_ok());
+    }
+
+    #[test]
+    fn test_toml_with_defaults() {
+        let config: SolverConfig = toml::from_str(
+            r#"
+            max_iterations = 5000
+            seed = 7
+            stop_conditions = [{ no_improvement = 200 }]
+
+            [schedule]
+            type = "geometric"
+            alpha = 0.95
+
+            [initial_temperature]
+            fixed = 10.0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.schedule, ScheduleConfig::Geometric { alpha: 0.95 });
+        assert_eq!(config.sampling, Sampling::RandomScan);
+        assert_eq!(config.max_iterations, 5000);
+
+        let sa = SimulatedAnnealingBuilder::try_from(config).unwrap().build();
+
+        assert_eq!(sa.seed, Some(7));
+        assert_eq!(sa.max_k, 5000);
+    }
+
+    #[test]
+    fn test_nested_defaults() {
+        let config: SolverConfig = serde_json::from_str(
+            r#"{
+                "adaptive_cooling": {"target": "lam_delosme", "window": 100},
+                "reheating": {"stagnation": 50}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.adaptive_cooling,
+            Some(AdaptiveCooling::new(AcceptanceTarget::LamDelosme, 100))
+        );
+        assert_eq!(config.reheating, Some(Reheating::new(50)));
+        assert!(SimulatedAnnealingBuilder::try_from(config).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        let config: SolverConfig =
+            serde_json::from_str(r#"{"schedule": {"type": "geometric", "alpha": 1.5}}"#).unwrap();
+
+        assert!(SimulatedAnnealingBuilder::try_from(config).is_err());
+        assert!(serde_json::from_str::<SolverConfig>(r#"{"max_k": 10}"#).is_err());
+    }
+}
@@ -1,4 +1,17 @@
+pub mod acceptance;
+pub mod atsp;
 pub mod builder;
+pub mod checkpoint;
+pub mod config;
+pub mod matrix;
+pub mod metric;
+pub mod multi_start;
+pub mod observer;
+pub mod parallel_tempering;
+pub mod schedule;
 pub mod simulated_annealing;
+pub mod steps;
+pub mod stop;
+pub mod stream;
 pub mod tsp;
-pub mod tsp2;
+pub mod tsplib;
@@ -0,0 +1,345 @@
+use std::sync::Arc;
+
+use anyhow::{bail, ensure};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    metric::Metric,
+    simulated_annealing::State,
+    tsp::{apply_move, move_delta, propose_move, tour_length, Move, Neighborhood, Point},
+};
+
+/// Square matrix of distances between `len()` nodes, stored row by row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawMatrix")]
+pub struct DistanceMatrix {
+    n: usize,
+    weights: Vec<f64>,
+}
+
+impl DistanceMatrix {
+    /// Matrix from its `n * n` entries in row-major order, where `weights[i * n + j]`
+    /// is the cost of going from `i` to `j`.
+    pub fn new(n: usize, weights: Vec<f64>) -> anyhow::Result<Self> {
+        ensure!(
+            weights.len() == n * n,
+            "expected {} weights for {n} nodes, got {}",
+            n * n,
+            weights.len()
+        );
+
+        if let Some(weight) = weights.iter().find(|weight| !weight.is_finite()) {
+            bail!("weights must be finite, got {weight}");
+        }
+
+        Ok(Self { n, weights })
+    }
+
+    pub fn from_rows(rows: Vec<Vec<f64>>) -> anyhow::Result<Self> {
+        let n = rows.len();
+
+        for (i, row) in rows.iter().enumerate() {
+            ensure!(
+                row.len() == n,
+                "row {i} has {} weights, expected {n}",
+                row.len()
+            );
+        }
+
+        Self::new(n, rows.into_iter().flatten().collect())
+    }
+
+    pub fn from_fn<F>(n: usize, mut distance: F) -> Self
+    where
+        F: FnMut(usize, usize) -> f64,
+    {
+        let weights = (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| distance(i, j))
+            .collect();
+
+        Self { n, weights }
+    }
+
+    pub fn from_points(points: &[Point], metric: Metric) -> Self {
+        Self::from_fn(points.len(), |i, j| metric.distance(&points[i], &points[j]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        self.weights[i * self.n + j]
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        (0..self.n).all(|i| (0..i).all(|j| self.get(i, j) == self.get(j, i)))
+    }
+}
+
+/// Checks that `tour` visits each of the `n` nodes of a matrix exactly once.
+pub(crate) fn check_tour(tour: &[usize], n: usize) -> anyhow::Result<()> {
+    let mut seen = vec![false; n];
+
+    ensure!(tour.len() == n, "expected {n} nodes, got {}", tour.len());
+
+    for &node in tour {
+        ensure!(
+            node < n && !seen[node],
+            "{node} is out of range or repeated"
+        );
+        seen[node] = true;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct RawMatrix {
+    n: usize,
+    weights: Vec<f64>,
+}
+
+impl TryFrom<RawMatrix> for DistanceMatrix {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawMatrix) -> anyhow::Result<Self> {
+        Self::new(raw.n, raw.weights)
+    }
+}
+
+/// Symmetric TSP over the nodes of a [`DistanceMatrix`], the tour being a permutation
+/// of their indices.
+///
+/// The matrix sits behind an [`Arc`], so cloning the state for the best tour, replicas
+/// or chains only copies the tour.
+///
+/// Only the tour and the neighborhood are serialized, as a [`MatrixTour`]: deserialize
+/// that and attach the matrix again with [`MatrixTsp::from_tour`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "MatrixTour")]
+pub struct MatrixTsp {
+    matrix: Arc<DistanceMatrix>,
+    tour: Vec<usize>,
+    neighborhood: Neighborhood,
+}
+
+impl MatrixTsp {
+    /// Tour visiting the nodes in index order. Fails if the matrix is not symmetric.
+    pub fn new(matrix: Arc<DistanceMatrix>) -> anyhow::Result<Self> {
+        ensure!(
+            matrix.is_symmetric(),
+            "the distance matrix is not symmetric, use an Atsp instead"
+        );
+
+        Ok(Self {
+            tour: (0..matrix.len()).collect(),
+            matrix,
+            neighborhood: Neighborhood::default(),
+        })
+    }
+
+    pub fn with_tour(mut self, tour: Vec<usize>) -> anyhow::Result<Self> {
+        check_tour(&tour, self.matrix.len())?;
+        self.tour = tour;
+
+        Ok(self)
+    }
+
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        self.neighborhood = neighborhood;
+
+        self
+    }
+
+    pub fn tour(&self) -> &[usize] {
+        &self.tour
+    }
+
+    pub fn matrix(&self) -> &Arc<DistanceMatrix> {
+        &self.matrix
+    }
+
+    /// Tour saved from a `MatrixTsp` over `matrix`.
+    pub fn from_tour(matrix: Arc<DistanceMatrix>, saved: MatrixTour) -> anyhow::Result<Self> {
+        Self::new(matrix)?
+            .with_neighborhood(saved.neighborhood)
+            .with_tour(saved.tour)
+    }
+}
+
+/// Wire format of a [`MatrixTsp`], without its matrix.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatrixTour {
+    pub tour: Vec<usize>,
+    pub neighborhood: Neighborhood,
+}
+
+impl From<MatrixTsp> for MatrixTour {
+    fn from(tsp: MatrixTsp) -> Self {
+        Self {
+            tour: tsp.tour,
+            neighborhood: tsp.neighborhood,
+        }
+    }
+}
+
+impl State for MatrixTsp {
+    type Move = Move;
+
+    fn energy(&self) -> f64 {
+        tour_length(&self.tour, |&a, &b| self.matrix.get(a, b))
+    }
+
+    fn propose<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
+        propose_move(self.tour.len(), self.neighborhood, rng)
+    }
+
+    fn apply(&mut self, mv: &Move) {
+        apply_move(&mut self.tour, mv);
+    }
+
+    fn undo(&mut self, mv: &Move) {
+        self.apply(mv);
+    }
+
+    fn neighborhood_size(&self) -> usize {
+        let n = self.tour.len();
+
+        n * n.saturating_sub(1) / 2
+    }
+
+    fn delta_energy(&mut self, mv: &Move) -> f64 {
+        move_delta(&self.tour, mv, |&a, &b| self.matrix.get(a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        checkpoint::Checkpoint,
+        simulated_annealing::SimulatedAnnealing,
+        tsp::{fixtures::grid, Tsp},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_matrix() {
+        let matrix = DistanceMatrix::from_rows(vec![
+            vec![0.0, 1.0, 2.0],
+            vec![1.0, 0.0, 3.0],
+            vec![2.0, 3.0, 0.0],
+        ])
+        .unwrap();
+
+        assert_eq!(matrix.len(), 3);
+        assert_eq!(matrix.get(1, 2), 3.0);
+        assert!(matrix.is_symmetric());
+
+        assert!(DistanceMatrix::new(2, vec![0.0; 3]).is_err());
+        assert!(DistanceMatrix::new(1, vec![f64::NAN]).is_err());
+        assert!(DistanceMatrix::from_rows(vec![vec![0.0, 1.0], vec![0.0]]).is_err());
+
+        let asymmetric = DistanceMatrix::new(2, vec![0.0, 1.0, 2.0, 0.0]).unwrap();
+        assert!(MatrixTsp::new(Arc::new(asymmetric)).is_err());
+    }
+
+    #[test]
+    fn test_delta_energy() {
+        let matrix = DistanceMatrix::from_fn(6, |i, j| ((i * j) % 7 + i + j) as f64);
+        let mut tsp = MatrixTsp::new(Arc::new(matrix)).unwrap();
+
+        let moves = (0..6)
+            .tuple_combinations()
+            .flat_map(|(i, j)| [Move::Swap(i, j), Move::Reverse(i, j)]);
+
+        for mv in moves {
+            let before = tsp.energy();
+            let delta = tsp.delta_energy(&mv);
+
+            tsp.apply(&mv);
+            assert_eq!(tsp.energy() - before, delta, "{mv:?}");
+
+            tsp.undo(&mv);
+            assert_eq!(tsp.energy(), before);
+        }
+
+        assert!(tsp.clone().with_tour(vec![0, 1, 2, 3, 4, 4]).is_err());
+        assert!(tsp.with_tour(vec![5, 4, 3, 2, 1, 0]).is_ok());
+    }
+
+    #[test]
+    fn test_matches_points() {
+        let points = grid();
+
+        let matrix = DistanceMatrix::from_points(&points, Metric::Manhattan);
+        let matrix_tsp = MatrixTsp::new(Arc::new(matrix))
+            .unwrap()
+            .with_neighborhood(Neighborhood::TwoOpt);
+
+        let tsp = Tsp::new(points)
+            .with_metric(Metric::Manhattan)
+            .with_neighborhood(Neighborhood::TwoOpt);
+
+        let sa = SimulatedAnnealing::builder()
+            .with_max_iter(300)
+            .with_seed(0)
+            .build();
+
+        let report = sa.run(&matrix_tsp);
+
+        assert_eq!(report.best_energy, sa.run(&tsp).best_energy);
+        assert!(Arc::ptr_eq(report.best_state.matrix(), matrix_tsp.matrix()));
+    }
+
+    #[test]
+    fn test_serialized_without_matrix() {
+        assert!(serde_json::from_str::<DistanceMatrix>(r#"{"n":3,"weights":[0.0]}"#).is_err());
+
+        let matrix = Arc::new(DistanceMatrix::from_points(&grid(), Metric::Euclidean));
+        let tsp = MatrixTsp::new(matrix.clone())
+            .unwrap()
+            .with_neighborhood(Neighborhood::TwoOpt);
+
+        let sa = SimulatedAnnealing::builder()
+            .with_max_iter(100)
+            .with_seed(0)
+            .build();
+
+        let report = sa.run(&tsp);
+
+        let mut steps = sa.iter(&tsp);
+        steps.by_ref().take(500).for_each(drop);
+
+        let json = serde_json::to_string(&steps.checkpoint()).unwrap();
+        assert!(!json.contains("weights"));
+
+        let checkpoint = serde_json::from_str::<Checkpoint<MatrixTour>>(&json)
+            .unwrap()
+            .try_map(|saved| MatrixTsp::from_tour(matrix.clone(), saved))
+            .unwrap();
+
+        let mut resumed = sa.resume(checkpoint);
+        resumed.by_ref().for_each(drop);
+        let resumed = resumed.into_report();
+
+        assert_eq!(resumed.best_state.tour(), report.best_state.tour());
+        assert!(Arc::ptr_eq(resumed.best_state.matrix(), &matrix));
+        assert!(Arc::ptr_eq(resumed.final_state.matrix(), &matrix));
+
+        let repeated = MatrixTour {
+            tour: vec![0; 30],
+            neighborhood: Neighborhood::Swap,
+        };
+        assert!(MatrixTsp::from_tour(matrix, repeated).is_err());
+    }
+}
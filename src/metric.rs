@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tsp::Point;
+
+/// Distance between two [`Point`]s.
+///
+/// The geographic metrics read points as `Point(latitude, longitude)`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    #[default]
+    Euclidean,
+    /// Euclidean distance rounded to the nearest integer, TSPLIB's `EUC_2D`.
+    RoundedEuclidean,
+    /// Euclidean distance rounded up, TSPLIB's `CEIL_2D`.
+    CeilEuclidean,
+    Manhattan,
+    Chebyshev,
+    /// Great circle distance in kilometres between coordinates in decimal degrees.
+    Haversine,
+    /// TSPLIB's `GEO`: great circle distance in whole kilometres between coordinates
+    /// written as `DDD.MM` degrees and minutes.
+    Geo,
+    /// TSPLIB's `ATT` pseudo-Euclidean distance.
+    Att,
+}
+
+impl Metric {
+    pub fn distance(&self, a: &Point, b: &Point) -> f64 {
+        let (dx, dy) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+
+        match self {
+            Metric::Euclidean => a.distance(b),
+            Metric::RoundedEuclidean => nint(a.distance(b)),
+            Metric::CeilEuclidean => a.distance(b).ceil(),
+            Metric::Manhattan => dx + dy,
+            Metric::Chebyshev => dx.max(dy),
+            Metric::Haversine => haversine(a, b),
+            Metric::Geo => geo(a, b),
+            Metric::Att => {
+                let r = ((dx * dx + dy * dy) / 10.0).sqrt();
+                let t = nint(r);
+
+                if t < r {
+                    t + 1.0
+                } else {
+                    t
+                }
+            }
+        }
+    }
+}
+
+/// Nearest integer, as TSPLIB's `nint`.
+fn nint(x: f64) -> f64 {
+    (x + 0.5).floor()
+}
+
+fn haversine(a: &Point, b: &Point) -> f64 {
+    const EARTH_RADIUS: f64 = 6371.0;
+
+    let (latitude_a, latitude_b) = (a.0.to_radians(), b.0.to_radians());
+    let d_latitude = latitude_b - latitude_a;
+    let d_longitude = (b.1 - a.1).to_radians();
+
+    let h = (d_latitude / 2.0).sin().powi(2)
+        + latitude_a.cos() * latitude_b.cos() * (d_longitude / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+fn geo(a: &Point, b: &Point) -> f64 {
+    // The truncated value of the reference implementation, needed to match its distances.
+    #[allow(clippy::approx_constant)]
+    const PI: f64 = 3.141592;
+    const RRR: f64 = 6378.388;
+
+    let radians = |x: f64| {
+        let degrees = x.trunc();
+        let minutes = x - degrees;
+
+        PI * (degrees + 5.0 * minutes / 3.0) / 180.0
+    };
+
+    let (latitude_a, longitude_a) = (radians(a.0), radians(a.1));
+    let (latitude_b, longitude_b) = (radians(b.0), radians(b.1));
+
+    let q1 = (longitude_a - longitude_b).cos();
+    let q2 = (latitude_a - latitude_b).cos();
+    let q3 = (latitude_a + latitude_b).cos();
+
+    (RRR * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0).trunc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_planar_metrics() {
+        let (a, b) = (Point(0.0, 0.0), Point(3.0, -4.5));
+
+        assert!((Metric::Euclidean.distance(&a, &b) - 29.25f64.sqrt()).abs() < 1e-12);
+        assert_eq!(Metric::RoundedEuclidean.distance(&a, &b), 5.0);
+        assert_eq!(Metric::CeilEuclidean.distance(&a, &b), 6.0);
+        assert_eq!(Metric::Manhattan.distance(&a, &b), 7.5);
+        assert_eq!(Metric::Chebyshev.distance(&a, &b), 4.5);
+
+        assert_eq!(Metric::Att.distance(&a, &Point(30.0, 40.0)), 16.0);
+        assert_eq!(Metric::Att.distance(&a, &Point(0.0, 10.0)), 4.0);
+    }
+
+    #[test]
+    fn test_geographic_metrics() {
+        let paris = Point(48.8566, 2.3522);
+        let london = Point(51.5074, -0.1278);
+
+        let distance = Metric::Haversine.distance(&paris, &london);
+        assert!((distance - 343.6).abs() < 1.0, "{distance}");
+        assert_eq!(Metric::Haversine.distance(&paris, &paris), 0.0);
+
+        let origin = Point(0.0, 0.0);
+
+        assert_eq!(Metric::Geo.distance(&origin, &Point(0.0, 1.0)), 112.0);
+        assert_eq!(Metric::Geo.distance(&origin, &Point(0.0, 0.3)), 56.0);
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::{
+    observer::Observer,
+    simulated_annealing::{RunReport, SimulatedAnnealing, State},
+    stop::StopReason,
+};
+
+/// Statistics of one chain of a multi-start run.
+#[derive(Debug, Clone)]
+pub struct ChainSummary {
+    pub seed: u64,
+    pub best_energy: f64,
+    pub final_energy: f64,
+    pub iterations: usize,
+    pub evaluations: usize,
+    pub accepted: usize,
+    pub reheats: usize,
+    pub stop_reason: StopReason,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiStartReport<S> {
+    /// Full report of the chain that reached the lowest energy.
+    pub best: RunReport<S>,
+    pub best_chain: usize,
+    pub chains: Vec<ChainSummary>,
+    pub elapsed: Duration,
+}
+
+impl<O> SimulatedAnnealing<O> {
+    /// Runs `chains` independent chains from `state` on rayon's thread pool and keeps
+    /// the best one. With a configured seed, the seed of every chain is derived from it,
+    /// so the whole batch is reproducible.
+    pub fn run_multi_start<S>(&self, state: &S, chains: usize) -> MultiStartReport<S>
+    where
+        S: State + Clone + Send + Sync,
+        O: Observer<S> + Sync,
+    {
+        assert!(chains > 0, "chains must be positive");
+
+        let start = Instant::now();
+
+        let mut seeder = self.rng();
+        let seeds: Vec<u64> = (0..chains).map(|_| seeder.gen()).collect();
+
+        let reports: Vec<_> = seeds
+            .par_iter()
+            .map(|&seed| self.run_with_rng(state, &mut ChaCha8Rng::seed_from_u64(seed)))
+            .collect();
+
+        let chains = seeds
+            .iter()
+            .zip(&reports)
+            .map(|(&seed, report)| ChainSummary {
+                seed,
+                best_energy: report.best_energy,
+                final_energy: report.final_energy,
+                iterations: report.iterations,
+                evaluations: report.evaluations,
+                accepted: report.accepted,
+                reheats: report.reheats,
+                stop_reason: report.stop_reason,
+                elapsed: report.elapsed,
+            })
+            .collect();
+
+        let (best_chain, best) = reports
+            .into_iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.best_energy.total_cmp(&b.best_energy))
+            .expect("there is at least one chain");
+
+        MultiStartReport {
+            best,
+            best_chain,
+            chains,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::tsp::{Point, Tsp};
+
+    use super::*;
+
+    #[test]
+    fn test_multi_start() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let state: Vec<_> = (0..30).map(|_| Point(rng.gen(), rng.gen())).collect();
+
+        let tsp = Tsp::new(state);
+
+        let sa = SimulatedAnnealing::builder()
+            .with_max_iter(300)
+            .with_seed(7)
+            .build();
+
+        let report = sa.run_multi_start(&tsp, 8);
+
+        assert_eq!(report.chains.len(), 8);
+        assert_eq!(
+            report.chains[report.best_chain].best_energy,
+            report.best.best_energy
+        );
+        assert!(report
+            .chains
+            .iter()
+            .all(|chain| chain.best_energy >= report.best.best_energy));
+
+        let again = sa.run_multi_start(&tsp, 8);
+
+        assert_eq!(
+            again.best.best_state.points(),
+            report.best.best_state.points()
+        );
+        assert_eq!(
+            again.chains.iter().map(|c| c.seed).collect_vec(),
+            report.chains.iter().map(|c| c.seed).collect_vec()
+        );
+    }
+}
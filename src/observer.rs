@@ -0,0 +1,121 @@
+use crate::simulated_annealing::RunReport;
+
+/// Callbacks invoked while a run progresses. Every method defaults to doing nothing.
+///
+/// Methods take `&self` so that a configured solver can be shared; observers that keep
+/// state need interior mutability or a channel.
+pub trait Observer<S> {
+    fn on_accept(&self, _state: &S, _energy: f64) {}
+
+    fn on_new_best(&self, _state: &S, _energy: f64) {}
+
+    /// Called at the start of the first level and of every level where the temperature
+    /// differs from the previous one.
+    fn on_temperature_change(&self, _k: usize, _t: f64) {}
+
+    fn on_finish(&self, _report: &RunReport<S>) {}
+}
+
+impl<S> Observer<S> for () {}
+
+impl<S, O> Observer<S> for &O
+where
+    O: Observer<S>,
+{
+    fn on_accept(&self, state: &S, energy: f64) {
+        (*self).on_accept(state, energy)
+    }
+
+    fn on_new_best(&self, state: &S, energy: f64) {
+        (*self).on_new_best(state, energy)
+    }
+
+    fn on_temperature_change(&self, k: usize, t: f64) {
+        (*self).on_temperature_change(k, t)
+    }
+
+    fn on_finish(&self, report: &RunReport<S>) {
+        (*self).on_finish(report)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use std::cell::RefCell;
+
+    use crate::simulated_annealing::State;
+
+    use super::*;
+
+    /// Observer keeping every energy and temperature it is told about.
+    #[derive(Default)]
+    pub(crate) struct Recorder {
+        pub(crate) accepted: RefCell<Vec<f64>>,
+        pub(crate) bests: RefCell<Vec<f64>>,
+        pub(crate) temperatures: RefCell<Vec<f64>>,
+        pub(crate) finished: RefCell<Option<f64>>,
+    }
+
+    impl<S: State> Observer<S> for Recorder {
+        fn on_accept(&self, state: &S, energy: f64) {
+            assert!((state.energy() - energy).abs() < 1e-9);
+            self.accepted.borrow_mut().push(energy);
+        }
+
+        fn on_new_best(&self, _state: &S, energy: f64) {
+            self.bests.borrow_mut().push(energy);
+        }
+
+        fn on_temperature_change(&self, _k: usize, t: f64) {
+            self.temperatures.borrow_mut().push(t);
+        }
+
+        fn on_finish(&self, report: &RunReport<S>) {
+            *self.finished.borrow_mut() = Some(report.best_energy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        simulated_annealing::SimulatedAnnealing,
+        tsp::{fixtures::grid, Tsp},
+    };
+
+    use super::fixtures::Recorder;
+
+    #[test]
+    fn test_observer() {
+        let tsp = Tsp::new(grid());
+
+        let recorder = Recorder::default();
+
+        let report = SimulatedAnnealing::builder()
+            .with_max_iter(50)
+            .with_seed(0)
+            .with_observer(&recorder)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(recorder.accepted.borrow().len(), report.accepted);
+        assert_eq!(recorder.temperatures.borrow().len(), report.iterations);
+        assert!(recorder.bests.borrow().windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(*recorder.finished.borrow(), Some(report.best_energy));
+    }
+
+    #[test]
+    fn test_temperature_changes_only() {
+        let recorder = Recorder::default();
+
+        let report = SimulatedAnnealing::builder()
+            .with_temperature_and_max_iter(|k| if k < 20 { 1.0 } else { 0.5 }, 50)
+            .with_seed(0)
+            .with_observer(&recorder)
+            .build()
+            .run(&Tsp::new(grid()));
+
+        assert_eq!(report.iterations, 50);
+        assert_eq!(*recorder.temperatures.borrow(), vec![1.0, 0.5]);
+    }
+}
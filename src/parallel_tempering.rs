@@ -0,0 +1,286 @@
+use std::time::{Duration, Instant};
+
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng, SeedableRng,
+};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::{acceptance::metropolis, simulated_annealing::State};
+
+/// Runs one replica of a state per temperature, doing Metropolis steps in parallel on
+/// rayon's thread pool, and periodically tries to swap the states of neighboring
+/// temperatures.
+pub struct ParallelTempering {
+    temperatures: Vec<f64>,
+    rounds: usize,
+    steps_per_round: usize,
+    seed: Option<u64>,
+}
+
+pub struct ParallelTemperingBuilder {
+    temperatures: Vec<f64>,
+    rounds: usize,
+    steps_per_round: usize,
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParallelTemperingReport<S> {
+    pub best_state: S,
+    pub best_energy: f64,
+    /// Final states, ordered like the temperatures.
+    pub replicas: Vec<S>,
+    pub temperatures: Vec<f64>,
+    /// Accepted over proposed moves of each replica.
+    pub acceptance_rates: Vec<f64>,
+    /// Swap attempts between temperatures `i` and `i + 1`.
+    pub swap_attempts: Vec<usize>,
+    pub swap_accepts: Vec<usize>,
+    pub rounds: usize,
+    pub elapsed: Duration,
+}
+
+impl<S> ParallelTemperingReport<S> {
+    pub fn swap_rates(&self) -> Vec<f64> {
+        self.swap_attempts
+            .iter()
+            .zip(&self.swap_accepts)
+            .map(|(&attempts, &accepts)| accepts as f64 / attempts.max(1) as f64)
+            .collect()
+    }
+}
+
+struct Replica<S> {
+    state: S,
+    energy: f64,
+    best_state: S,
+    best_energy: f64,
+    rng: ChaCha8Rng,
+    evaluations: usize,
+    accepted: usize,
+}
+
+impl<S> Replica<S>
+where
+    S: State + Clone,
+{
+    fn sweep(&mut self, t: f64, steps: usize) {
+        if self.state.neighborhood_size() == 0 {
+            return;
+        }
+
+        let uniform = Uniform::new_inclusive(0.0, 1.0);
+
+        for _ in 0..steps {
+            let mv = self.state.propose(&mut self.rng);
+            let delta = self.state.delta_energy(&mv);
+            self.evaluations += 1;
+
+            if metropolis(delta, t) >= uniform.sample(&mut self.rng) {
+                self.state.apply(&mv);
+                self.energy += delta;
+                self.accepted += 1;
+
+                if self.energy < self.best_energy {
+                    self.best_state.clone_from(&self.state);
+                    self.best_energy = self.energy;
+                }
+            }
+        }
+    }
+}
+
+impl Default for ParallelTemperingBuilder {
+    fn default() -> Self {
+        ParallelTemperingBuilder {
+            temperatures: geometric_ladder(0.01, 1.0, 8),
+            rounds: 1000,
+            steps_per_round: 100,
+            seed: None,
+        }
+    }
+}
+
+/// `n` temperatures from `min` to `max` with a constant ratio between neighbors.
+pub fn geometric_ladder(min: f64, max: f64, n: usize) -> Vec<f64> {
+    assert!(0.0 < min && min <= max, "need 0 < min <= max");
+    assert!(n > 0, "n must be positive");
+
+    if n == 1 {
+        return vec![min];
+    }
+
+    let ratio = (max / min).powf(1.0 / (n - 1) as f64);
+
+    (0..n).map(|i| min * ratio.powi(i as i32)).collect()
+}
+
+impl ParallelTemperingBuilder {
+    pub fn new() -> ParallelTemperingBuilder {
+        ParallelTemperingBuilder::default()
+    }
+
+    pub fn with_temperatures(mut self, mut temperatures: Vec<f64>) -> Self {
+        assert!(!temperatures.is_empty(), "need at least one temperature");
+        assert!(
+            temperatures.iter().all(|&t| t > 0.0),
+            "temperatures must be positive"
+        );
+
+        temperatures.sort_by(f64::total_cmp);
+        self.temperatures = temperatures;
+
+        self
+    }
+
+    pub fn with_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+
+        self
+    }
+
+    /// Metropolis steps every replica makes between two rounds of swaps.
+    pub fn with_steps_per_round(mut self, steps_per_round: usize) -> Self {
+        self.steps_per_round = steps_per_round;
+
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+
+        self
+    }
+
+    pub fn build(self) -> ParallelTempering {
+        ParallelTempering {
+            temperatures: self.temperatures,
+            rounds: self.rounds,
+            steps_per_round: self.steps_per_round,
+            seed: self.seed,
+        }
+    }
+}
+
+impl ParallelTempering {
+    pub fn builder() -> ParallelTemperingBuilder {
+        ParallelTemperingBuilder::default()
+    }
+
+    pub fn run<S>(&self, state: &S) -> ParallelTemperingReport<S>
+    where
+        S: State + Clone + Send,
+    {
+        let start = Instant::now();
+
+        let mut rng = match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        };
+        let uniform = Uniform::new_inclusive(0.0, 1.0);
+
+        let energy = state.energy();
+
+        let mut replicas: Vec<_> = self
+            .temperatures
+            .iter()
+            .map(|_| Replica {
+                state: state.clone(),
+                energy,
+                best_state: state.clone(),
+                best_energy: energy,
+                rng: ChaCha8Rng::seed_from_u64(rng.gen()),
+                evaluations: 0,
+                accepted: 0,
+            })
+            .collect();
+
+        let n_pairs = replicas.len() - 1;
+        let mut swap_attempts = vec![0; n_pairs];
+        let mut swap_accepts = vec![0; n_pairs];
+
+        for round in 0..self.rounds {
+            replicas
+                .par_iter_mut()
+                .zip(self.temperatures.par_iter())
+                .for_each(|(replica, &t)| replica.sweep(t, self.steps_per_round));
+
+            // Alternate between even and odd pairs so that each replica takes part in at
+            // most one swap per round.
+            for i in (round % 2..n_pairs).step_by(2) {
+                let (t_i, t_j) = (self.temperatures[i], self.temperatures[i + 1]);
+                let (e_i, e_j) = (replicas[i].energy, replicas[i + 1].energy);
+
+                swap_attempts[i] += 1;
+
+                let log_ratio = (1.0 / t_i - 1.0 / t_j) * (e_i - e_j);
+
+                if log_ratio >= 0.0 || f64::exp(log_ratio) >= uniform.sample(&mut rng) {
+                    swap_accepts[i] += 1;
+
+                    let (left, right) = replicas.split_at_mut(i + 1);
+                    let (a, b) = (&mut left[i], &mut right[0]);
+
+                    std::mem::swap(&mut a.state, &mut b.state);
+                    std::mem::swap(&mut a.energy, &mut b.energy);
+                }
+            }
+        }
+
+        let best = replicas
+            .iter()
+            .min_by(|a, b| a.best_energy.total_cmp(&b.best_energy))
+            .expect("there is at least one replica");
+
+        ParallelTemperingReport {
+            best_state: best.best_state.clone(),
+            best_energy: best.best_energy,
+            acceptance_rates: replicas
+                .iter()
+                .map(|r| r.accepted as f64 / r.evaluations.max(1) as f64)
+                .collect(),
+            replicas: replicas.into_iter().map(|r| r.state).collect(),
+            temperatures: self.temperatures.clone(),
+            swap_attempts,
+            swap_accepts,
+            rounds: self.rounds,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tsp::fixtures::shuffled_polygon;
+
+    use super::*;
+
+    #[test]
+    fn test_parallel_tempering() {
+        let (tsp, best_energy) = shuffled_polygon(20);
+
+        let pt = ParallelTempering::builder()
+            .with_temperatures(vec![0.01, 0.03, 0.1, 0.3])
+            .with_rounds(200)
+            .with_steps_per_round(200)
+            .with_seed(0)
+            .build();
+
+        let report = pt.run(&tsp);
+
+        assert!((report.best_energy - best_energy).abs() < 1e-4);
+        assert_eq!(report.replicas.len(), 4);
+        assert_eq!(report.swap_attempts.iter().sum::<usize>(), 300);
+        assert!(report.swap_rates().iter().all(|&rate| rate > 0.0));
+
+        for replica in &report.replicas {
+            assert!(replica.energy() >= report.best_energy - 1e-9);
+        }
+
+        let again = pt.run(&tsp);
+
+        assert_eq!(again.best_state.points(), report.best_state.points());
+    }
+}
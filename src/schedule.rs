@@ -0,0 +1,534 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::simulated_annealing::State;
+
+/// How the temperature at iteration 0 is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitialTemperature {
+    Fixed(f64),
+    /// Sample `samples` random moves from the initial state and pick the temperature at
+    /// which a fraction `acceptance` of the uphill ones would be accepted.
+    Calibrated {
+        acceptance: f64,
+        samples: usize,
+    },
+}
+
+/// Temperature at which the Metropolis rule accepts, on average, a fraction `acceptance`
+/// of the uphill moves proposed from `state`.
+///
+/// Downhill moves are always accepted, so they are left out as in Ben-Ameur's method:
+/// counting them would make any target below their share unreachable.
+pub fn calibrate_initial_temperature<S, R>(
+    state: &mut S,
+    acceptance: f64,
+    samples: usize,
+    rng: &mut R,
+) -> f64
+where
+    S: State,
+    R: Rng + ?Sized,
+{
+    assert!(
+        0.0 < acceptance && acceptance < 1.0,
+        "acceptance must be in (0, 1), got {acceptance}"
+    );
+
+    if state.neighborhood_size() == 0 {
+        return 1.0;
+    }
+
+    let uphill: Vec<_> = (0..samples)
+        .map(|_| {
+            let mv = state.propose(rng);
+            state.delta_energy(&mv)
+        })
+        .filter(|&delta| delta > 0.0)
+        .collect();
+
+    if uphill.is_empty() {
+        return 1.0;
+    }
+
+    let ratio = |t: f64| uphill.iter().map(|d| f64::exp(-d / t)).sum::<f64>() / uphill.len() as f64;
+
+    // The ratio grows with t, so bracket the target and bisect in log space.
+    let mut hi = uphill.iter().sum::<f64>() / uphill.len() as f64;
+    while ratio(hi) < acceptance && hi < f64::MAX / 2.0 {
+        hi *= 2.0;
+    }
+
+    let mut lo = hi;
+    while ratio(lo) > acceptance && lo > f64::MIN_POSITIVE * 2.0 {
+        lo /= 2.0;
+    }
+
+    for _ in 0..64 {
+        let mid = (lo * hi).sqrt();
+
+        if ratio(mid) < acceptance {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    hi.max(f64::MIN_POSITIVE)
+}
+
+/// Temperature as a function of the initial temperature `t0` and the iteration `k`.
+///
+/// The built-in schedules stay positive for every `k`. The engine also raises any
+/// temperature below `f64::MIN_POSITIVE` to it, which covers closures going to zero.
+pub trait CoolingSchedule {
+    fn temperature(&self, t0: f64, k: usize) -> f64;
+}
+
+/// Plain closures keep working as schedules; they ignore `t0`.
+impl<F> CoolingSchedule for F
+where
+    F: Fn(usize) -> f64,
+{
+    fn temperature(&self, _t0: f64, k: usize) -> f64 {
+        self(k)
+    }
+}
+
+/// `t0 - rate * k`, never going below `floor`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Linear {
+    rate: f64,
+    floor: f64,
+}
+
+impl Linear {
+    pub fn new(rate: f64, floor: f64) -> Self {
+        assert!(rate > 0.0, "rate must be positive, got {rate}");
+        assert!(floor > 0.0, "floor must be positive, got {floor}");
+
+        Self { rate, floor }
+    }
+}
+
+impl CoolingSchedule for Linear {
+    fn temperature(&self, t0: f64, k: usize) -> f64 {
+        (t0 - self.rate * k as f64).max(self.floor)
+    }
+}
+
+/// `t0 * alpha^k`, kept at least `f64::MIN_POSITIVE` once it would underflow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometric {
+    alpha: f64,
+}
+
+impl Geometric {
+    pub fn new(alpha: f64) -> Self {
+        assert!(
+            0.0 < alpha && alpha < 1.0,
+            "alpha must be in (0, 1), got {alpha}"
+        );
+
+        Self { alpha }
+    }
+}
+
+impl CoolingSchedule for Geometric {
+    fn temperature(&self, t0: f64, k: usize) -> f64 {
+        (t0 * self.alpha.powf(k as f64)).max(f64::MIN_POSITIVE)
+    }
+}
+
+/// `t0 * ln(2) / ln(k + 2)`, the classic slow schedule shifted so that it starts at `t0`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Logarithmic;
+
+impl CoolingSchedule for Logarithmic {
+    fn temperature(&self, t0: f64, k: usize) -> f64 {
+        t0 * std::f64::consts::LN_2 / (k as f64 + 2.0).ln()
+    }
+}
+
+/// Lundy–Mees: `t_{k+1} = t_k / (1 + beta * t_k)`, i.e. `t0 / (1 + k * beta * t0)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LundyMees {
+    beta: f64,
+}
+
+impl LundyMees {
+    pub fn new(beta: f64) -> Self {
+        assert!(beta > 0.0, "beta must be positive, got {beta}");
+
+        Self { beta }
+    }
+}
+
+impl CoolingSchedule for LundyMees {
+    fn temperature(&self, t0: f64, k: usize) -> f64 {
+        t0 / (1.0 + k as f64 * self.beta * t0)
+    }
+}
+
+/// `floor + (t0 - floor) * exp(-rate * k)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialWithFloor {
+    rate: f64,
+    floor: f64,
+}
+
+impl ExponentialWithFloor {
+    pub fn new(rate: f64, floor: f64) -> Self {
+        assert!(rate > 0.0, "rate must be positive, got {rate}");
+        assert!(floor > 0.0, "floor must be positive, got {floor}");
+
+        Self { rate, floor }
+    }
+}
+
+impl CoolingSchedule for ExponentialWithFloor {
+    fn temperature(&self, t0: f64, k: usize) -> f64 {
+        self.floor + (t0 - self.floor).max(0.0) * f64::exp(-self.rate * k as f64)
+    }
+}
+
+/// `t0 / k^exponent`, with `k = 0` treated as `k = 1`. `PowerLaw::new(1.2)` with
+/// `t0 = 10.0` is the schedule used by the binaries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerLaw {
+    exponent: f64,
+}
+
+impl PowerLaw {
+    pub fn new(exponent: f64) -> Self {
+        assert!(exponent > 0.0, "exponent must be positive, got {exponent}");
+
+        Self { exponent }
+    }
+}
+
+impl CoolingSchedule for PowerLaw {
+    fn temperature(&self, t0: f64, k: usize) -> f64 {
+        t0 / (k as f64).max(1.0).powf(self.exponent)
+    }
+}
+
+/// Acceptance ratio an [`AdaptiveCooling`] run aims for, as a function of the fraction
+/// `s` of the run already done.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcceptanceTarget {
+    /// Decays geometrically from `initial` to `last`.
+    Geometric { initial: f64, last: f64 },
+    /// The piecewise curve from Lam and Delosme: a quick drop from 1.0 to 0.44, a long
+    /// plateau, then a decay towards 0.
+    LamDelosme,
+}
+
+impl AcceptanceTarget {
+    pub fn at(&self, s: f64) -> f64 {
+        let s = s.clamp(0.0, 1.0);
+
+        match *self {
+            AcceptanceTarget::Geometric { initial, last } => initial * (last / initial).powf(s),
+            AcceptanceTarget::LamDelosme => {
+                if s < 0.15 {
+                    0.44 + 0.56 * 560f64.powf(-s / 0.15)
+                } else if s < 0.65 {
+                    0.44
+                } else {
+                    0.44 * 440f64.powf(-(s - 0.65) / 0.35)
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the cooling schedule by a controller that measures the acceptance ratio
+/// over windows of `window` proposals and multiplies the temperature by
+/// `exp(gain * (target - ratio))`: it cools faster while too many moves are accepted
+/// and slower, or even warms up, while too few are.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveCooling {
+    pub(crate) target: AcceptanceTarget,
+    pub(crate) window: usize,
+    #[serde(default = "default_gain")]
+    pub(crate) gain: f64,
+}
+
+fn default_gain() -> f64 {
+    1.0
+}
+
+impl AdaptiveCooling {
+    pub fn new(target: AcceptanceTarget, window: usize) -> Self {
+        assert!(window > 0, "window must be positive");
+
+        Self {
+            target,
+            window,
+            gain: default_gain(),
+        }
+    }
+
+    pub fn with_gain(mut self, gain: f64) -> Self {
+        assert!(gain > 0.0, "gain must be positive, got {gain}");
+        self.gain = gain;
+
+        self
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Next temperature after a window with acceptance `ratio`, `s` being the fraction
+    /// of the run already done.
+    pub fn adjust(&self, t: f64, ratio: f64, s: f64) -> f64 {
+        t * f64::exp(self.gain * (self.target.at(s) - ratio))
+    }
+}
+
+/// Length of the Markov chain run at each temperature.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepsPerTemperature {
+    Fixed(usize),
+    /// A multiple of the neighborhood size of the state, at least one step.
+    Proportional(f64),
+}
+
+impl StepsPerTemperature {
+    pub fn steps(&self, neighborhood_size: usize) -> usize {
+        match *self {
+            StepsPerTemperature::Fixed(steps) => steps,
+            StepsPerTemperature::Proportional(factor) => {
+                ((factor * neighborhood_size as f64).ceil() as usize).max(1)
+            }
+        }
+    }
+}
+
+/// Raises the temperature again when a run stagnates: after `stagnation` iterations
+/// without improving the best energy, or when no move gets accepted, the schedule
+/// restarts from `fraction` of the initial temperature.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Reheating {
+    pub(crate) stagnation: usize,
+    #[serde(default = "default_fraction")]
+    pub(crate) fraction: f64,
+    #[serde(default)]
+    pub(crate) restart_from_best: bool,
+    #[serde(default)]
+    pub(crate) max_reheats: Option<usize>,
+}
+
+fn default_fraction() -> f64 {
+    1.0
+}
+
+impl Reheating {
+    pub fn new(stagnation: usize) -> Self {
+        assert!(stagnation > 0, "stagnation must be positive");
+
+        Self {
+            stagnation,
+            fraction: default_fraction(),
+            restart_from_best: false,
+            max_reheats: None,
+        }
+    }
+
+    /// Closures passed to
+    /// [`with_temperature_and_max_iter`](crate::builder::SimulatedAnnealingBuilder::with_temperature_and_max_iter)
+    /// ignore the initial temperature, so with them `fraction` has no effect and a reheat
+    /// only restarts the closure from `k = 0`.
+    pub fn with_fraction(mut self, fraction: f64) -> Self {
+        assert!(fraction > 0.0, "fraction must be positive, got {fraction}");
+        self.fraction = fraction;
+
+        self
+    }
+
+    /// Also jumps back to the best state seen so far on every reheat.
+    pub fn restarting_from_best(mut self) -> Self {
+        self.restart_from_best = true;
+
+        self
+    }
+
+    pub fn with_max_reheats(mut self, max_reheats: usize) -> Self {
+        self.max_reheats = Some(max_reheats);
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{seq::SliceRandom, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::tsp::{Neighborhood, Point, Tsp};
+
+    use super::*;
+
+    /// A walk on the integers where every move costs `scale` up or down.
+    #[derive(Clone)]
+    struct Walk {
+        scale: f64,
+        position: i64,
+    }
+
+    impl State for Walk {
+        type Move = i64;
+
+        fn energy(&self) -> f64 {
+            self.scale * self.position as f64
+        }
+
+        fn propose<R: Rng + ?Sized>(&self, rng: &mut R) -> Self::Move {
+            if rng.gen() {
+                1
+            } else {
+                -1
+            }
+        }
+
+        fn apply(&mut self, mv: &Self::Move) {
+            self.position += mv;
+        }
+
+        fn undo(&mut self, mv: &Self::Move) {
+            self.position -= mv;
+        }
+
+        fn neighborhood_size(&self) -> usize {
+            2
+        }
+    }
+
+    fn schedules() -> Vec<Box<dyn CoolingSchedule>> {
+        vec![
+            Box::new(Linear::new(0.01, 1e-3)),
+            Box::new(Geometric::new(0.95)),
+            Box::new(Logarithmic),
+            Box::new(LundyMees::new(0.5)),
+            Box::new(ExponentialWithFloor::new(0.1, 1e-3)),
+            Box::new(PowerLaw::new(1.2)),
+        ]
+    }
+
+    #[test]
+    fn test_starts_at_t0() {
+        for schedule in schedules() {
+            assert!((schedule.temperature(10.0, 0) - 10.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_positive_and_non_increasing() {
+        for schedule in schedules() {
+            // Far enough for the geometric schedule to underflow.
+            let temperatures: Vec<_> = (0..100_000)
+                .map(|k| schedule.temperature(10.0, k))
+                .collect();
+
+            assert!(temperatures.iter().all(|&t| t > 0.0));
+            assert!(temperatures.windows(2).all(|w| w[1] <= w[0]));
+        }
+    }
+
+    #[test]
+    fn test_power_law_matches_binaries() {
+        let schedule = PowerLaw::new(1.2);
+
+        for k in 1..100 {
+            assert_eq!(schedule.temperature(10.0, k), 10.0 / (k as f64).powf(1.2));
+        }
+    }
+
+    #[test]
+    fn test_floors() {
+        assert_eq!(Linear::new(0.01, 1e-3).temperature(1.0, 1000), 1e-3);
+        assert!((ExponentialWithFloor::new(1.0, 0.5).temperature(1.0, 1000) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rejects_invalid_alpha() {
+        Geometric::new(1.5);
+    }
+
+    #[test]
+    fn test_calibration_scales_with_instance() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let mut unit = Walk {
+            scale: 1.0,
+            position: 0,
+        };
+        let mut kilometres = Walk {
+            scale: 1000.0,
+            position: 0,
+        };
+
+        let t_unit = calibrate_initial_temperature(&mut unit, 0.8, 1000, &mut rng);
+        let t_km = calibrate_initial_temperature(&mut kilometres, 0.8, 1000, &mut rng);
+
+        // Every uphill move costs the same, so each is accepted with probability 0.8.
+        assert!((f64::exp(-1.0 / t_unit) - 0.8).abs() < 1e-9);
+        assert!((t_km / t_unit - 1000.0).abs() < 100.0);
+    }
+
+    #[test]
+    fn test_calibration_without_moves() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut tsp = Tsp::new(vec![Point(0.0, 0.0)]);
+
+        assert_eq!(
+            calibrate_initial_temperature(&mut tsp, 0.8, 10, &mut rng),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_calibration_below_downhill_share() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let mut points: Vec<_> = (0..200).map(|_| Point(rng.gen(), rng.gen())).collect();
+        points.shuffle(&mut rng);
+        let mut tsp = Tsp::new(points).with_neighborhood(Neighborhood::TwoOpt);
+
+        for acceptance in [0.1, 0.3, 0.5] {
+            let t0 = calibrate_initial_temperature(&mut tsp, acceptance, 1000, &mut rng);
+
+            assert!(t0 > 0.0, "{acceptance}: {t0}");
+        }
+
+        // Rises with the target even though most moves from a random tour are downhill.
+        let t_low = calibrate_initial_temperature(&mut tsp, 0.3, 1000, &mut rng);
+        let t_high = calibrate_initial_temperature(&mut tsp, 0.5, 1000, &mut rng);
+
+        assert!(t_low < t_high);
+    }
+
+    #[test]
+    fn test_lam_delosme_target() {
+        let target = AcceptanceTarget::LamDelosme;
+
+        assert!((target.at(0.0) - 1.0).abs() < 1e-12);
+        assert!((target.at(0.15) - 0.44).abs() < 1e-3);
+        assert_eq!(target.at(0.5), 0.44);
+        assert!((target.at(1.0) - 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_adaptive_cooling_direction() {
+        let adaptive = AdaptiveCooling::new(AcceptanceTarget::LamDelosme, 100);
+
+        assert!(adaptive.adjust(1.0, 0.9, 0.5) < 1.0);
+        assert!(adaptive.adjust(1.0, 0.1, 0.5) > 1.0);
+    }
+}
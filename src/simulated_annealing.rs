@@ -1,29 +1,109 @@
-use crate::builder::SimulatedAnnealingBuilder;
-use std::ops::Deref;
-
-use rand::{
-    distributions::{Distribution, Uniform},
-    thread_rng,
+use crate::{
+    acceptance::Acceptance,
+    builder::SimulatedAnnealingBuilder,
+    observer::Observer,
+    schedule::{
+        AdaptiveCooling, CoolingSchedule, InitialTemperature, Linear, Reheating,
+        StepsPerTemperature,
+    },
+    steps::Steps,
+    stop::{Progress, StopCondition, StopReason},
 };
+use std::{sync::Arc, time::Duration};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
 pub trait State {
-    fn acceptability(&self, new: &Self, t: f64) -> f64;
+    type Move;
 
-    fn get_next_states(&self) -> impl Iterator<Item = Self>
-    where
-        Self: Clone;
+    fn energy(&self) -> f64;
+
+    fn propose<R: Rng + ?Sized>(&self, rng: &mut R) -> Self::Move;
+
+    fn apply(&mut self, mv: &Self::Move);
+
+    fn undo(&mut self, mv: &Self::Move);
+
+    /// Number of distinct moves available from a state.
+    fn neighborhood_size(&self) -> usize;
+
+    /// Energy difference caused by applying `mv`.
+    ///
+    /// The default applies the move, measures it and undoes it. Override it when the
+    /// change can be computed incrementally.
+    fn delta_energy(&mut self, mv: &Self::Move) -> f64 {
+        let before = self.energy();
+
+        self.apply(mv);
+        let after = self.energy();
+        self.undo(mv);
+
+        after - before
+    }
+}
+
+/// How each step of the chain picks its transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sampling {
+    /// Propose a single random move and accept or reject it.
+    Metropolis,
+    /// Keep proposing random moves, drawn with replacement, until one is accepted. A scan
+    /// is bounded by about `n ln n` proposals for a neighborhood of `n` moves, so some
+    /// moves may be tried twice and others skipped. The run freezes when a whole scan is
+    /// rejected.
+    #[default]
+    RandomScan,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunReport<S> {
+    pub best_state: S,
+    pub best_energy: f64,
+    pub final_state: S,
+    pub final_energy: f64,
+    pub initial_temperature: f64,
+    pub iterations: usize,
+    pub evaluations: usize,
+    pub accepted: usize,
+    pub reheats: usize,
+    pub stop_reason: StopReason,
+    pub elapsed: Duration,
 }
 
-pub struct SimulatedAnnealing {
-    pub(crate) temperature: Box<dyn Fn(usize) -> f64>,
+/// Solver configuration. It is cheap to clone and can be shared between threads as
+/// long as its observer can.
+#[derive(Clone)]
+pub struct SimulatedAnnealing<O = ()> {
+    pub(crate) schedule: Arc<dyn CoolingSchedule + Send + Sync>,
+    pub(crate) initial_temperature: InitialTemperature,
+    pub(crate) adaptive_cooling: Option<AdaptiveCooling>,
+    pub(crate) reheating: Option<Reheating>,
+    pub(crate) steps_per_temperature: StepsPerTemperature,
+    pub(crate) sampling: Sampling,
+    pub(crate) acceptance: Acceptance,
     pub(crate) max_k: usize,
+    pub(crate) seed: Option<u64>,
+    pub(crate) stop_conditions: Vec<StopCondition>,
+    pub(crate) observer: O,
 }
 
 impl Default for SimulatedAnnealing {
     fn default() -> Self {
         Self {
-            temperature: Box::new(|k| 1.0 - 0.01 * k as f64),
+            schedule: Arc::new(Linear::new(0.01, 1e-3)),
+            initial_temperature: InitialTemperature::Fixed(1.0),
+            adaptive_cooling: None,
+            reheating: None,
+            steps_per_temperature: StepsPerTemperature::Fixed(1),
+            sampling: Sampling::default(),
+            acceptance: Acceptance::default(),
             max_k: 100,
+            seed: None,
+            stop_conditions: Vec::new(),
+            observer: (),
         }
     }
 }
@@ -36,32 +116,204 @@ impl SimulatedAnnealing {
     pub fn builder() -> SimulatedAnnealingBuilder {
         SimulatedAnnealingBuilder::default()
     }
+}
 
-    pub fn run<S>(&self, state: &S) -> S
+impl<O> SimulatedAnnealing<O> {
+    /// Runs from `state` with an RNG seeded from the configured seed, or from entropy
+    /// if none was set.
+    pub fn run<S>(&self, state: &S) -> RunReport<S>
     where
         S: State + Clone,
+        O: Observer<S>,
     {
-        let mut rng = thread_rng();
-        let uniform = Uniform::new_inclusive(0.0, 1.0);
+        self.run_with_rng(state, &mut self.rng())
+    }
 
-        let mut current_state = state.clone();
+    pub fn run_with_rng<S, R>(&self, state: &S, rng: &mut R) -> RunReport<S>
+    where
+        S: State + Clone,
+        O: Observer<S>,
+        R: Rng + ?Sized,
+    {
+        let mut steps = Steps::new(self, state, rng);
 
-        let temperature = self.temperature.deref();
+        steps.by_ref().for_each(drop);
 
-        for k in 0..self.max_k {
-            let t = temperature(k);
+        steps.into_report()
+    }
 
-            let next_state = current_state.get_next_states().find(|candidate| {
-                current_state.acceptability(candidate, t) >= uniform.sample(&mut rng)
-            });
+    /// Steps through a run lazily, seeding the RNG like [`SimulatedAnnealing::run`].
+    pub fn iter<S>(&self, state: &S) -> Steps<'_, S, O, ChaCha8Rng>
+    where
+        S: State + Clone,
+        O: Observer<S>,
+    {
+        Steps::new(self, state, self.rng())
+    }
 
-            if let Some(next_state) = next_state {
-                current_state = next_state;
-            } else {
-                break;
-            }
+    pub(crate) fn rng(&self) -> ChaCha8Rng {
+        match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
         }
+    }
+
+    /// Temperature at iteration `k`, kept strictly positive whatever the schedule returns.
+    pub(crate) fn temperature(&self, t0: f64, k: usize) -> f64 {
+        self.schedule.temperature(t0, k).max(f64::MIN_POSITIVE)
+    }
+
+    pub(crate) fn should_stop(&self, progress: &Progress) -> Option<StopReason> {
+        self.stop_conditions
+            .iter()
+            .find_map(|condition| condition.check(progress))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        observer::fixtures::Recorder,
+        schedule::{AcceptanceTarget, AdaptiveCooling, Geometric, Reheating, StepsPerTemperature},
+        tsp::{
+            fixtures::{grid, shuffled_polygon},
+            Point, Tsp,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_adaptive_cooling() {
+        let (tsp, best_energy) = shuffled_polygon(20);
+
+        let report = SimulatedAnnealing::builder()
+            .with_calibrated_initial_temperature(0.9, 200)
+            .with_adaptive_cooling(AdaptiveCooling::new(AcceptanceTarget::LamDelosme, 50))
+            .with_max_iter(3000)
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert!((report.best_energy - best_energy).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_reheating() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let state: Vec<_> = (0..30).map(|_| Point(rng.gen(), rng.gen())).collect();
+
+        let tsp = Tsp::new(state);
+
+        let recorder = Recorder::default();
+
+        let frozen = SimulatedAnnealing::builder()
+            .with_schedule(Geometric::new(0.5))
+            .with_max_iter(10_000)
+            .with_seed(0)
+            .with_observer(&recorder)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(frozen.stop_reason, StopReason::Frozen);
+        assert_eq!(frozen.reheats, 0);
+        assert!(recorder
+            .temperatures
+            .borrow()
+            .windows(2)
+            .all(|t| t[1] <= t[0]));
+
+        let recorder = Recorder::default();
+
+        let reheated = SimulatedAnnealing::builder()
+            .with_schedule(Geometric::new(0.5))
+            .with_max_iter(10_000)
+            .with_reheating(
+                Reheating::new(50)
+                    .with_fraction(0.25)
+                    .restarting_from_best()
+                    .with_max_reheats(3),
+            )
+            .with_seed(0)
+            .with_observer(&recorder)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(reheated.stop_reason, StopReason::Frozen);
+        assert_eq!(reheated.reheats, 3);
+
+        let temperatures = recorder.temperatures.borrow();
+        let rises: Vec<_> = temperatures
+            .windows(2)
+            .filter(|t| t[1] > t[0])
+            .map(|t| t[1])
+            .collect();
+
+        assert_eq!(rises, vec![0.25; 3]);
+    }
+
+    #[test]
+    fn test_steps_per_temperature() {
+        let (tsp, best_energy) = shuffled_polygon(20);
+
+        let report = SimulatedAnnealing::builder()
+            .with_schedule(Geometric::new(0.9))
+            .with_steps_per_temperature(StepsPerTemperature::Proportional(0.5))
+            .with_max_iter(60)
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert!(report.iterations <= 60);
+        assert!(report.accepted > report.iterations);
+        assert!((report.best_energy - best_energy).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_metropolis_sampling() {
+        let (tsp, best_energy) = shuffled_polygon(20);
+
+        let report = SimulatedAnnealing::builder()
+            .with_sampling(Sampling::Metropolis)
+            .with_schedule(Geometric::new(0.95))
+            .with_steps_per_temperature(StepsPerTemperature::Proportional(2.0))
+            .with_max_iter(200)
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::MaxIterations);
+        assert_eq!(report.evaluations, 200 * 380);
+        assert!(report.accepted < report.evaluations);
+        assert!((report.best_energy - best_energy).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_solver_is_shareable() {
+        fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+
+        assert_send_sync_clone::<SimulatedAnnealing>();
+        assert_send_sync_clone::<crate::builder::SimulatedAnnealingBuilder>();
+
+        let tsp = Tsp::new(grid());
+
+        let sa = SimulatedAnnealing::builder()
+            .with_schedule(Geometric::new(0.99))
+            .with_seed(0)
+            .build();
+
+        let expected = sa.run(&tsp).best_energy;
+
+        std::thread::scope(|scope| {
+            let shared = scope.spawn(|| sa.run(&tsp).best_energy);
+
+            let cloned = sa.clone();
+            let tsp = &tsp;
+            let owned = scope.spawn(move || cloned.run(tsp).best_energy);
 
-        current_state
+            assert_eq!(shared.join().unwrap(), expected);
+            assert_eq!(owned.join().unwrap(), expected);
+        });
     }
 }
@@ -0,0 +1,496 @@
+use std::time::Instant;
+
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    acceptance::AcceptanceContext,
+    checkpoint::Checkpoint,
+    observer::Observer,
+    schedule::{calibrate_initial_temperature, InitialTemperature},
+    simulated_annealing::{RunReport, Sampling, SimulatedAnnealing, State},
+    stop::{Progress, StopReason},
+};
+
+/// Proposals needed to see (almost) every move of a neighborhood when drawing with
+/// replacement, following the coupon collector bound `n ln n`.
+fn scan_attempts(neighborhood_size: usize) -> usize {
+    let n = neighborhood_size as f64;
+
+    (n * n.ln().max(1.0)).ceil() as usize
+}
+
+/// Snapshot of one step of the chain: a single proposal with [`Sampling::Metropolis`],
+/// a whole bounded scan with [`Sampling::RandomScan`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub iteration: usize,
+    pub temperature: f64,
+    /// Energy of the current state after the step.
+    pub energy: f64,
+    pub accepted: bool,
+}
+
+/// Iterator over the steps of a run, created by [`SimulatedAnnealing::iter`].
+///
+/// The states live in the iterator, so they are reached through [`Steps::state`] and
+/// [`Steps::best_state`] between calls to `next`.
+pub struct Steps<'a, S, O, R> {
+    sa: &'a SimulatedAnnealing<O>,
+    rng: R,
+    uniform: Uniform<f64>,
+    start: Instant,
+
+    current_state: S,
+    current_energy: f64,
+    best_state: S,
+    best_energy: f64,
+    initial_energy: f64,
+
+    t0: f64,
+    t: f64,
+    t_start: f64,
+    last_reheat: usize,
+    reheats: usize,
+    /// Last temperature passed to the observer, NaN before the first level.
+    reported_t: f64,
+
+    k: usize,
+    step: usize,
+    steps: usize,
+    attempts: usize,
+    improved: bool,
+    window_start: (usize, usize),
+
+    iterations: usize,
+    evaluations: usize,
+    accepted: usize,
+    stagnation: usize,
+    stop_reason: Option<StopReason>,
+}
+
+impl<'a, S, O, R> Steps<'a, S, O, R>
+where
+    S: State + Clone,
+    O: Observer<S>,
+    R: Rng,
+{
+    pub(crate) fn new(sa: &'a SimulatedAnnealing<O>, state: &S, mut rng: R) -> Self {
+        let start = Instant::now();
+
+        let mut current_state = state.clone();
+        let current_energy = current_state.energy();
+
+        let t0 = match sa.initial_temperature {
+            InitialTemperature::Fixed(t0) => t0,
+            InitialTemperature::Calibrated {
+                acceptance,
+                samples,
+            } => calibrate_initial_temperature(&mut current_state, acceptance, samples, &mut rng),
+        };
+
+        Self {
+            sa,
+            rng,
+            uniform: Uniform::new_inclusive(0.0, 1.0),
+            start,
+            best_state: current_state.clone(),
+            best_energy: current_energy,
+            current_state,
+            current_energy,
+            initial_energy: current_energy,
+            t0,
+            t: t0,
+            t_start: t0,
+            last_reheat: 0,
+            reheats: 0,
+            reported_t: f64::NAN,
+            k: 0,
+            step: 0,
+            steps: 0,
+            attempts: 0,
+            improved: false,
+            window_start: (0, 0),
+            iterations: 0,
+            evaluations: 0,
+            accepted: 0,
+            stagnation: 0,
+            stop_reason: None,
+        }
+    }
+
+    pub fn state(&self) -> &S {
+        &self.current_state
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.current_energy
+    }
+
+    pub fn best_state(&self) -> &S {
+        &self.best_state
+    }
+
+    pub fn best_energy(&self) -> f64 {
+        self.best_energy
+    }
+
+    /// Why the run ended, or `None` while it can still make steps.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// Temperature levels completed so far.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Consumes the iterator into the report of the run so far and notifies the
+    /// observer that the run finished.
+    pub fn into_report(self) -> RunReport<S> {
+        let report = RunReport {
+            best_state: self.best_state,
+            best_energy: self.best_energy,
+            final_state: self.current_state,
+            final_energy: self.current_energy,
+            initial_temperature: self.t0,
+            iterations: self.iterations,
+            evaluations: self.evaluations,
+            accepted: self.accepted,
+            reheats: self.reheats,
+            stop_reason: self.stop_reason.unwrap_or(StopReason::Interrupted),
+            elapsed: self.start.elapsed(),
+        };
+
+        self.sa.observer.on_finish(&report);
+
+        report
+    }
+
+    fn begin_level(&mut self) {
+        let sa = self.sa;
+
+        if sa.adaptive_cooling.is_none() {
+            self.t = sa.temperature(self.t_start, self.k - self.last_reheat);
+        }
+
+        let neighborhood_size = self.current_state.neighborhood_size();
+
+        self.steps = sa.steps_per_temperature.steps(neighborhood_size);
+        self.attempts = match sa.sampling {
+            _ if neighborhood_size == 0 => 0,
+            Sampling::Metropolis => 1,
+            Sampling::RandomScan => scan_attempts(neighborhood_size),
+        };
+        self.improved = false;
+
+        if self.t != self.reported_t {
+            self.reported_t = self.t;
+            sa.observer.on_temperature_change(self.k, self.t);
+        }
+    }
+
+    fn end_level(&mut self, frozen: bool) {
+        let sa = self.sa;
+
+        if self.improved {
+            self.stagnation = 0;
+        } else {
+            self.stagnation += 1;
+        }
+
+        if let Some(adaptive) = &sa.adaptive_cooling {
+            let window_evaluations = self.evaluations - self.window_start.0;
+
+            if window_evaluations >= adaptive.window() {
+                let ratio =
+                    (self.accepted - self.window_start.1) as f64 / window_evaluations as f64;
+                let s = self.k as f64 / sa.max_k as f64;
+
+                self.t = adaptive.adjust(self.t, ratio, s).max(f64::MIN_POSITIVE);
+                self.window_start = (self.evaluations, self.accepted);
+            }
+        }
+
+        let stagnating = sa.reheating.is_some_and(|reheating| {
+            self.stagnation >= reheating.stagnation
+                && self.k - self.last_reheat >= reheating.stagnation
+        });
+
+        if frozen || stagnating {
+            match sa.reheating {
+                Some(reheating) if reheating.max_reheats.is_none_or(|max| self.reheats < max) => {
+                    self.reheats += 1;
+                    self.last_reheat = self.k + 1;
+                    self.t_start = self.t0 * reheating.fraction;
+                    self.t = self.t_start;
+
+                    if reheating.restart_from_best {
+                        self.current_state.clone_from(&self.best_state);
+                        self.current_energy = self.best_energy;
+                    }
+                }
+                _ if frozen => {
+                    self.stop_reason = Some(StopReason::Frozen);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        self.iterations += 1;
+        self.k += 1;
+        self.step = 0;
+    }
+
+    /// Proposes moves until one is accepted or `attempts` run out. Returns `None` when a
+    /// stop condition fired.
+    fn transition(&mut self) -> Option<bool> {
+        for _ in 0..self.attempts {
+            if !self.sa.stop_conditions.is_empty() {
+                let progress = Progress {
+                    start: self.start,
+                    evaluations: self.evaluations,
+                    best_energy: self.best_energy,
+                    stagnation: self.stagnation,
+                    temperature: self.t,
+                };
+
+                if let Some(reason) = self.sa.should_stop(&progress) {
+                    self.stop_reason = Some(reason);
+                    return None;
+                }
+            }
+
+            let mv = self.current_state.propose(&mut self.rng);
+            let delta = self.current_state.delta_energy(&mv);
+            self.evaluations += 1;
+
+            let context = AcceptanceContext {
+                delta,
+                temperature: self.t,
+                current_energy: self.current_energy,
+                best_energy: self.best_energy,
+                initial_energy: self.initial_energy,
+                evaluations: self.evaluations,
+            };
+
+            if self.sa.acceptance.probability(&context) >= self.uniform.sample(&mut self.rng) {
+                self.current_state.apply(&mv);
+                self.current_energy += delta;
+
+                return Some(true);
+            }
+        }
+
+        Some(false)
+    }
+}
+
+impl<'a, S, O> Steps<'a, S, O, ChaCha8Rng>
+where
+    S: State + Clone,
+    O: Observer<S>,
+{
+    pub(crate) fn from_checkpoint(
+        sa: &'a SimulatedAnnealing<O>,
+        checkpoint: Checkpoint<S>,
+    ) -> Self {
+        let now = Instant::now();
+
+        Self {
+            sa,
+            rng: checkpoint.rng,
+            uniform: Uniform::new_inclusive(0.0, 1.0),
+            start: now.checked_sub(checkpoint.elapsed).unwrap_or(now),
+            current_state: checkpoint.current_state,
+            current_energy: checkpoint.current_energy,
+            best_state: checkpoint.best_state,
+            best_energy: checkpoint.best_energy,
+            initial_energy: checkpoint.initial_energy,
+            t0: checkpoint.t0,
+            t: checkpoint.t,
+            t_start: checkpoint.t_start,
+            last_reheat: checkpoint.last_reheat,
+            reheats: checkpoint.reheats,
+            reported_t: f64::NAN,
+            k: checkpoint.k,
+            step: checkpoint.step,
+            steps: checkpoint.steps,
+            attempts: checkpoint.attempts,
+            improved: checkpoint.improved,
+            window_start: checkpoint.window_start,
+            iterations: checkpoint.iterations,
+            evaluations: checkpoint.evaluations,
+            accepted: checkpoint.accepted,
+            stagnation: checkpoint.stagnation,
+            stop_reason: checkpoint.stop_reason,
+        }
+    }
+
+    /// Snapshot of the run that [`SimulatedAnnealing::resume`] continues from.
+    pub fn checkpoint(&self) -> Checkpoint<S> {
+        Checkpoint {
+            current_state: self.current_state.clone(),
+            current_energy: self.current_energy,
+            best_state: self.best_state.clone(),
+            best_energy: self.best_energy,
+            initial_energy: self.initial_energy,
+            t0: self.t0,
+            t: self.t,
+            t_start: self.t_start,
+            last_reheat: self.last_reheat,
+            reheats: self.reheats,
+            k: self.k,
+            step: self.step,
+            steps: self.steps,
+            attempts: self.attempts,
+            improved: self.improved,
+            window_start: self.window_start,
+            iterations: self.iterations,
+            evaluations: self.evaluations,
+            accepted: self.accepted,
+            stagnation: self.stagnation,
+            stop_reason: self.stop_reason,
+            elapsed: self.start.elapsed(),
+            rng: self.rng.clone(),
+        }
+    }
+}
+
+impl<S, O, R> Iterator for Steps<'_, S, O, R>
+where
+    S: State + Clone,
+    O: Observer<S>,
+    R: Rng,
+{
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        if self.stop_reason.is_some() {
+            return None;
+        }
+
+        while self.step == 0 {
+            if self.k >= self.sa.max_k {
+                self.stop_reason = Some(StopReason::MaxIterations);
+                return None;
+            }
+
+            self.begin_level();
+
+            // A state without moves, such as a tour of one city, cannot go anywhere.
+            if self.attempts == 0 {
+                self.stop_reason = Some(StopReason::Frozen);
+                return None;
+            }
+
+            if self.steps > 0 {
+                break;
+            }
+
+            self.end_level(false);
+        }
+
+        let accepted = self.transition()?;
+
+        if accepted {
+            self.accepted += 1;
+            self.sa
+                .observer
+                .on_accept(&self.current_state, self.current_energy);
+
+            if self.current_energy < self.best_energy {
+                self.best_state.clone_from(&self.current_state);
+                self.best_energy = self.current_energy;
+                self.improved = true;
+                self.sa
+                    .observer
+                    .on_new_best(&self.best_state, self.best_energy);
+            }
+        }
+
+        let step = Step {
+            iteration: self.k,
+            temperature: self.t,
+            energy: self.current_energy,
+            accepted,
+        };
+
+        self.step += 1;
+
+        let frozen = !accepted && self.sa.sampling == Sampling::RandomScan;
+
+        if frozen || self.step >= self.steps {
+            self.end_level(frozen);
+        }
+
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tsp::{fixtures::grid, Point, Tsp};
+
+    use super::*;
+
+    #[test]
+    fn test_empty_neighborhood_freezes() {
+        for points in [vec![], vec![Point(0.0, 0.0)]] {
+            let tsp = Tsp::new(points);
+
+            for sampling in [Sampling::Metropolis, Sampling::RandomScan] {
+                let report = SimulatedAnnealing::builder()
+                    .with_sampling(sampling)
+                    .with_seed(0)
+                    .build()
+                    .run(&tsp);
+
+                assert_eq!(report.stop_reason, StopReason::Frozen);
+                assert_eq!(report.evaluations, 0);
+                assert_eq!(report.best_energy, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iter_matches_run() {
+        let tsp = Tsp::new(grid());
+
+        let sa = SimulatedAnnealing::builder()
+            .with_max_iter(200)
+            .with_seed(0)
+            .build();
+
+        let report = sa.run(&tsp);
+
+        let mut steps = sa.iter(&tsp);
+        let mut history = Vec::new();
+
+        while let Some(step) = steps.next() {
+            if step.accepted {
+                history.push(steps.state().clone());
+            }
+        }
+
+        assert_eq!(history.len(), report.accepted);
+        assert_eq!(
+            history.last().unwrap().points(),
+            report.final_state.points()
+        );
+        assert_eq!(steps.into_report().best_energy, report.best_energy);
+
+        let mut steps = sa.iter(&tsp);
+        let taken = steps
+            .by_ref()
+            .take_while(|step| step.iteration < 10)
+            .count();
+
+        assert_eq!(taken, 10);
+        assert_eq!(steps.into_report().stop_reason, StopReason::Interrupted);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    MaxIterations,
+    /// No proposed move was accepted during a scan.
+    Frozen,
+    TimeLimit,
+    TargetEnergy,
+    NoImprovement,
+    MinTemperature,
+    MaxEvaluations,
+    Cancelled,
+    /// The caller stopped driving [`Steps`](crate::steps::Steps) before the run ended.
+    Interrupted,
+}
+
+/// Shared flag to stop a run from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Extra conditions that end a run before `max_k` iterations. A run stops as soon as
+/// any of them holds.
+///
+/// Every condition but [`StopCondition::Cancelled`] can be (de)serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopCondition {
+    /// The clock is only read every 64 evaluations, so a run may overshoot the limit by
+    /// that many.
+    TimeLimit(Duration),
+    TargetEnergy(f64),
+    /// Iterations in a row without improving the best energy.
+    NoImprovement(usize),
+    MinTemperature(f64),
+    MaxEvaluations(usize),
+    #[serde(skip)]
+    Cancelled(CancellationToken),
+}
+
+/// Evaluations between two reads of the clock for [`StopCondition::TimeLimit`].
+const CLOCK_INTERVAL: usize = 64;
+
+pub(crate) struct Progress {
+    pub(crate) start: Instant,
+    pub(crate) evaluations: usize,
+    pub(crate) best_energy: f64,
+    pub(crate) stagnation: usize,
+    pub(crate) temperature: f64,
+}
+
+impl StopCondition {
+    pub(crate) fn check(&self, progress: &Progress) -> Option<StopReason> {
+        let stop = match self {
+            StopCondition::TimeLimit(limit) => {
+                progress.evaluations.is_multiple_of(CLOCK_INTERVAL)
+                    && progress.start.elapsed() >= *limit
+            }
+            StopCondition::TargetEnergy(target) => progress.best_energy <= *target,
+            StopCondition::NoImprovement(n) => progress.stagnation >= *n,
+            StopCondition::MinTemperature(min) => progress.temperature < *min,
+            StopCondition::MaxEvaluations(max) => progress.evaluations >= *max,
+            StopCondition::Cancelled(token) => token.is_cancelled(),
+        };
+
+        stop.then_some(self.reason())
+    }
+
+    fn reason(&self) -> StopReason {
+        match self {
+            StopCondition::TimeLimit(_) => StopReason::TimeLimit,
+            StopCondition::TargetEnergy(_) => StopReason::TargetEnergy,
+            StopCondition::NoImprovement(_) => StopReason::NoImprovement,
+            StopCondition::MinTemperature(_) => StopReason::MinTemperature,
+            StopCondition::MaxEvaluations(_) => StopReason::MaxEvaluations,
+            StopCondition::Cancelled(_) => StopReason::Cancelled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        simulated_annealing::{Sampling, SimulatedAnnealing, State},
+        tsp::{fixtures::grid, Tsp},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_stop_conditions() {
+        let tsp = Tsp::new(grid());
+
+        let report = SimulatedAnnealing::builder()
+            .with_temperature_and_max_iter(|_| 100.0, 1000)
+            .with_stop_condition(StopCondition::MaxEvaluations(50))
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::MaxEvaluations);
+        assert_eq!(report.evaluations, 50);
+
+        let report = SimulatedAnnealing::builder()
+            .with_temperature_and_max_iter(|k| 1.0 / (k + 1) as f64, 1000)
+            .with_stop_condition(StopCondition::MinTemperature(0.1))
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::MinTemperature);
+        assert_eq!(report.iterations, 10);
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let report = SimulatedAnnealing::builder()
+            .with_stop_condition(StopCondition::Cancelled(token))
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::Cancelled);
+        assert_eq!(report.evaluations, 0);
+
+        let report = SimulatedAnnealing::builder()
+            .with_temperature_and_max_iter(|_| 100.0, usize::MAX)
+            .with_stop_condition(StopCondition::TimeLimit(Duration::from_millis(20)))
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::TimeLimit);
+        assert!(report.elapsed >= Duration::from_millis(20));
+        assert!(report.evaluations.is_multiple_of(CLOCK_INTERVAL));
+
+        let target = 0.8 * tsp.energy();
+
+        let report = SimulatedAnnealing::builder()
+            .with_stop_condition(StopCondition::TargetEnergy(target))
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::TargetEnergy);
+        assert!(report.best_energy <= target);
+
+        let report = SimulatedAnnealing::builder()
+            .with_sampling(Sampling::Metropolis)
+            .with_temperature_and_max_iter(|_| 1e-9, 100_000)
+            .with_stop_condition(StopCondition::NoImprovement(5))
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert_eq!(report.stop_reason, StopReason::NoImprovement);
+        assert!(report.iterations < 100_000);
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+use crate::{
+    observer::Observer,
+    simulated_annealing::{RunReport, SimulatedAnnealing, State},
+    steps::Step,
+};
+
+/// An accepted step together with the state it led to.
+#[derive(Debug, Clone)]
+pub struct Snapshot<S> {
+    pub step: Step,
+    pub state: S,
+    pub best_energy: f64,
+}
+
+/// Controls how many snapshots [`SimulatedAnnealing::into_stream`] produces.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamOptions {
+    buffer: usize,
+    every: usize,
+    min_interval: Duration,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            buffer: 16,
+            every: 1,
+            min_interval: Duration::ZERO,
+        }
+    }
+}
+
+impl StreamOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshots held before the annealing thread waits for the consumer.
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer must be positive");
+        self.buffer = buffer;
+
+        self
+    }
+
+    /// Only send every `every`-th accepted step.
+    pub fn with_every(mut self, every: usize) -> Self {
+        assert!(every > 0, "every must be positive");
+        self.every = every;
+
+        self
+    }
+
+    /// Skip the snapshots that come less than `min_interval` after the last one sent.
+    /// This drops frames rather than slowing the run down: to pace a consumer, read the
+    /// stream at its own rate and let the bounded buffer hold the run back.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+
+        self
+    }
+}
+
+/// Snapshots of a run happening on a blocking thread. The last accepted step is always
+/// sent, whatever the throttling.
+pub struct ProgressStream<S> {
+    receiver: ReceiverStream<Snapshot<S>>,
+    handle: JoinHandle<RunReport<S>>,
+}
+
+impl<S> ProgressStream<S> {
+    /// Stops listening and waits for the report. A run whose snapshots are not consumed
+    /// anymore ends with [`StopReason::Interrupted`](crate::stop::StopReason::Interrupted).
+    pub async fn report(self) -> RunReport<S> {
+        drop(self.receiver);
+
+        self.handle.await.expect("the annealing thread panicked")
+    }
+}
+
+impl<S> Stream for ProgressStream<S> {
+    type Item = Snapshot<S>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl<O> SimulatedAnnealing<O> {
+    /// Runs on Tokio's blocking pool and streams progress back. Must be called from
+    /// within a Tokio runtime.
+    pub fn into_stream<S>(self, state: S, options: StreamOptions) -> ProgressStream<S>
+    where
+        S: State + Clone + Send + 'static,
+        O: Observer<S> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(options.buffer);
+
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut steps = self.iter(&state);
+
+            let mut accepted = 0;
+            let mut last_sent: Option<Instant> = None;
+            let mut pending = None;
+
+            while let Some(step) = steps.next() {
+                if !step.accepted {
+                    continue;
+                }
+
+                accepted += 1;
+
+                let due = accepted % options.every == 0
+                    && last_sent.is_none_or(|sent| sent.elapsed() >= options.min_interval);
+
+                if !due {
+                    pending = Some(step);
+                    continue;
+                }
+
+                let snapshot = Snapshot {
+                    step,
+                    state: steps.state().clone(),
+                    best_energy: steps.best_energy(),
+                };
+
+                if sender.blocking_send(snapshot).is_err() {
+                    return steps.into_report();
+                }
+
+                pending = None;
+                last_sent = Some(Instant::now());
+            }
+
+            if let Some(step) = pending {
+                let snapshot = Snapshot {
+                    step,
+                    state: steps.state().clone(),
+                    best_energy: steps.best_energy(),
+                };
+
+                let _ = sender.blocking_send(snapshot);
+            }
+
+            steps.into_report()
+        });
+
+        ProgressStream {
+            receiver: ReceiverStream::new(receiver),
+            handle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use crate::{
+        stop::StopReason,
+        tsp::{fixtures::grid, Tsp},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream() {
+        let tsp = Tsp::new(grid());
+
+        let sa = || {
+            SimulatedAnnealing::builder()
+                .with_max_iter(200)
+                .with_seed(0)
+                .build()
+        };
+
+        let expected = sa().run(&tsp);
+
+        let mut stream = sa().into_stream(tsp.clone(), StreamOptions::new().with_every(7));
+        let mut snapshots = Vec::new();
+
+        while let Some(snapshot) = stream.next().await {
+            snapshots.push(snapshot);
+        }
+
+        let report = stream.report().await;
+
+        assert_eq!(snapshots.len(), expected.accepted.div_ceil(7));
+        assert_eq!(
+            snapshots.last().unwrap().state.points(),
+            expected.final_state.points()
+        );
+        assert_eq!(report.best_energy, expected.best_energy);
+
+        // The annealing thread waits on the full buffer until the stream is dropped.
+        let mut stream = sa().into_stream(tsp.clone(), StreamOptions::new().with_buffer(1));
+
+        assert!(stream.next().await.is_some());
+        assert_eq!(stream.report().await.stop_reason, StopReason::Interrupted);
+    }
+}
@@ -1,69 +1,268 @@
-use itertools::Itertools;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::simulated_annealing::State;
+use crate::{metric::Metric, simulated_annealing::State};
 
-#[derive(Debug, Clone)]
-struct Point(f64, f64);
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point(pub f64, pub f64);
 
 impl Point {
-    fn distance(&self, other: &Self) -> f64 {
+    pub fn distance(&self, other: &Self) -> f64 {
         ((self.0 - other.0).powi(2) + (self.1 - other.1).powi(2)).sqrt()
     }
 }
 
-#[derive(Clone, Debug)]
-struct Tsp {
+/// Moves proposed by a [`Tsp`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Neighborhood {
+    /// Exchange two cities of the tour.
+    #[default]
+    Swap,
+    /// Reverse a segment of the tour, replacing two edges by two others.
+    TwoOpt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Swap(usize, usize),
+    /// Reverses positions `i..=j`, with `i < j`.
+    Reverse(usize, usize),
+}
+
+/// Length of the closed tour visiting `tour` in order.
+pub(crate) fn tour_length<T, D>(tour: &[T], distance: D) -> f64
+where
+    D: Fn(&T, &T) -> f64,
+{
+    let n = tour.len();
+
+    (0..n).map(|e| distance(&tour[e], &tour[(e + 1) % n])).sum()
+}
+
+pub(crate) fn propose_move<R: Rng + ?Sized>(
+    n: usize,
+    neighborhood: Neighborhood,
+    rng: &mut R,
+) -> Move {
+    let i = rng.gen_range(0..n);
+    let j = rng.gen_range(0..n - 1);
+    let j = if j >= i { j + 1 } else { j };
+
+    match neighborhood {
+        Neighborhood::Swap => Move::Swap(i, j),
+        Neighborhood::TwoOpt => Move::Reverse(i.min(j), i.max(j)),
+    }
+}
+
+/// Applies `mv` to `tour`. Every move is its own inverse.
+pub(crate) fn apply_move<T>(tour: &mut [T], mv: &Move) {
+    match *mv {
+        Move::Swap(i, j) => tour.swap(i, j),
+        Move::Reverse(i, j) => tour[i..=j].reverse(),
+    }
+}
+
+/// Change of [`tour_length`] caused by `mv`, looking only at the edges it replaces.
+/// Reversals assume a symmetric `distance`.
+pub(crate) fn move_delta<T, D>(tour: &[T], mv: &Move, distance: D) -> f64
+where
+    D: Fn(&T, &T) -> f64,
+{
+    let n = tour.len();
+    let edge = |a: usize, b: usize| distance(&tour[a], &tour[b]);
+
+    match *mv {
+        Move::Swap(i, j) => {
+            let position = |k: usize| match k {
+                k if k == i => j,
+                k if k == j => i,
+                k => k,
+            };
+
+            // Only the edges touching positions `i` and `j` change.
+            let edges = [(i + n - 1) % n, i, (j + n - 1) % n, j];
+
+            edges
+                .iter()
+                .enumerate()
+                .filter(|&(idx, e)| !edges[..idx].contains(e))
+                .map(|(_, &e)| edge(position(e), position((e + 1) % n)) - edge(e, (e + 1) % n))
+                .sum()
+        }
+        Move::Reverse(i, j) => {
+            // Reversing the whole tour keeps the same edges.
+            if i == 0 && j == n - 1 {
+                return 0.0;
+            }
+
+            let before = (i + n - 1) % n;
+            let after = (j + 1) % n;
+
+            edge(before, j) + edge(i, after) - edge(before, i) - edge(j, after)
+        }
+    }
+}
+
+/// Closed tour visiting `state` in order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tsp {
     state: Vec<Point>,
+    neighborhood: Neighborhood,
+    metric: Metric,
+}
+
+/// Wire format of a tour, as sent to the web and gRPC clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Coordinates {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
 }
 
 impl Tsp {
-    fn energy(&self) -> f64 {
+    pub fn new(points: Vec<Point>) -> Self {
+        Self {
+            state: points,
+            neighborhood: Neighborhood::default(),
+            metric: Metric::default(),
+        }
+    }
+
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        self.neighborhood = neighborhood;
+
+        self
+    }
+
+    pub fn with_metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+
+        self
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.state
+    }
+
+    pub fn into_points(self) -> Vec<Point> {
         self.state
-            .iter()
-            .cycle()
-            .skip(1)
-            .take(self.state.len())
-            .zip(&self.state)
-            .map(|(next, previous)| next.distance(previous))
-            .sum()
+    }
+}
+
+impl From<&Tsp> for Coordinates {
+    fn from(tsp: &Tsp) -> Self {
+        let (x, y) = tsp.state.iter().map(|p| (p.0, p.1)).unzip();
+
+        Coordinates { x, y }
+    }
+}
+
+impl TryFrom<Coordinates> for Tsp {
+    type Error = anyhow::Error;
+
+    fn try_from(coordinates: Coordinates) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            coordinates.x.len() == coordinates.y.len(),
+            "got {} x coordinates but {} y coordinates",
+            coordinates.x.len(),
+            coordinates.y.len()
+        );
+
+        let points = coordinates
+            .x
+            .into_iter()
+            .zip(coordinates.y)
+            .map(|(x, y)| Point(x, y))
+            .collect();
+
+        Ok(Tsp::new(points))
     }
 }
 
 impl State for Tsp {
-    fn acceptability(&self, new: &Self, t: f64) -> f64 {
-        let new_energy = Tsp::energy(new);
-        let my_energy = self.energy();
-
-        if new_energy < my_energy {
-            1.0
-        } else {
-            f64::exp(-(new_energy - my_energy) / t)
-        }
+    type Move = Move;
+
+    fn energy(&self) -> f64 {
+        tour_length(&self.state, |a, b| self.metric.distance(a, b))
     }
 
-    fn get_next_states(&self) -> impl Iterator<Item = Self> {
-        let mut rng = thread_rng();
+    fn propose<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
+        propose_move(self.state.len(), self.neighborhood, rng)
+    }
+
+    fn apply(&mut self, mv: &Move) {
+        apply_move(&mut self.state, mv);
+    }
+
+    fn undo(&mut self, mv: &Move) {
+        self.apply(mv);
+    }
+
+    fn neighborhood_size(&self) -> usize {
         let n = self.state.len();
 
-        let mut swaps: Vec<_> = (0..n).tuple_combinations::<(usize, usize)>().collect();
+        n * n.saturating_sub(1) / 2
+    }
 
-        swaps.shuffle(&mut rng);
+    fn delta_energy(&mut self, mv: &Move) -> f64 {
+        move_delta(&self.state, mv, |a, b| self.metric.distance(a, b))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use rand::{seq::SliceRandom, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    /// Thirty points scattered over a 30 by 30 grid.
+    pub(crate) fn grid() -> Vec<Point> {
+        (0..30)
+            .map(|i| Point((i * 7 % 30) as f64, (i * 13 % 30) as f64))
+            .collect()
+    }
+
+    /// Vertices of a regular polygon visited in a shuffled order, along with the length
+    /// of the optimal tour around the polygon.
+    pub(crate) fn shuffled_polygon(n_vertices: i32) -> (Tsp, f64) {
+        let z =
+            num::complex::Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / n_vertices as f64);
 
-        swaps.into_iter().map(|(i, j)| {
-            let mut state = self.state.clone();
+        let state: Vec<_> = (0..n_vertices)
+            .map(|i| z.powi(i))
+            .map(|z| Point(z.re, z.im))
+            .collect();
+
+        let mut tsp = Tsp::new(state);
+
+        let best_energy = tsp.energy();
 
-            state.swap(i, j);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        tsp.state.shuffle(&mut rng);
 
-            Tsp { state }
-        })
+        (tsp, best_energy)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::simulated_annealing::SimulatedAnnealing;
+    use itertools::Itertools;
+
+    use super::{
+        fixtures::{grid, shuffled_polygon},
+        *,
+    };
+
+    use crate::{
+        schedule::{Geometric, StepsPerTemperature},
+        simulated_annealing::{Sampling, SimulatedAnnealing},
+        stop::StopReason,
+    };
 
     #[test]
     fn test_distance() {
@@ -72,12 +271,60 @@ mod tests {
 
     #[test]
     fn test_energy() {
-        let tsp = Tsp {
-            state: vec![Point(0.0, 0.0), Point(3.0, 0.0), Point(3.0, 4.0)],
-        };
+        let tsp = Tsp::new(vec![Point(0.0, 0.0), Point(3.0, 0.0), Point(3.0, 4.0)]);
         assert_eq!(tsp.energy(), 12.0);
     }
 
+    #[test]
+    fn test_delta_energy() {
+        let points = vec![
+            Point(0.0, 0.0),
+            Point(0.0, 2.0),
+            Point(2.0, 0.0),
+            Point(2.0, 2.0),
+            Point(1.0, 3.0),
+        ];
+
+        for metric in [Metric::Euclidean, Metric::Manhattan, Metric::Haversine] {
+            let mut tsp = Tsp::new(points.clone()).with_metric(metric);
+
+            let moves = (0..tsp.state.len())
+                .tuple_combinations()
+                .flat_map(|(i, j)| [Move::Swap(i, j), Move::Reverse(i, j)]);
+
+            for mv in moves {
+                let before = tsp.energy();
+                let delta = tsp.delta_energy(&mv);
+
+                tsp.apply(&mv);
+                assert!(
+                    (tsp.energy() - before - delta).abs() < 1e-9,
+                    "{metric:?} {mv:?}"
+                );
+
+                tsp.undo(&mv);
+                assert_eq!(tsp.energy(), before);
+            }
+        }
+    }
+
+    #[test]
+    fn test_metric() {
+        let points = vec![Point(0.0, 0.0), Point(3.0, 4.0), Point(3.0, 0.0)];
+
+        assert_eq!(Tsp::new(points.clone()).energy(), 12.0);
+        assert_eq!(
+            Tsp::new(points.clone())
+                .with_metric(Metric::Manhattan)
+                .energy(),
+            14.0
+        );
+        assert_eq!(
+            Tsp::new(points).with_metric(Metric::Chebyshev).energy(),
+            11.0
+        );
+    }
+
     #[test]
     fn test_tsp() {
         let state = vec![
@@ -89,22 +336,20 @@ mod tests {
             Point(1.0, -1.0),
         ];
 
-        let tsp = Tsp { state };
+        let tsp = Tsp::new(state);
 
-        let sa = SimulatedAnnealing::new();
+        let sa = SimulatedAnnealing::builder().with_seed(0).build();
 
-        let final_state = sa.run(&tsp);
+        let final_state = sa.run(&tsp).best_state;
 
-        let correct_result = Tsp {
-            state: vec![
-                Point(0.0, 0.0),
-                Point(0.0, 2.0),
-                Point(1.0, 3.0),
-                Point(2.0, 2.0),
-                Point(2.0, 0.0),
-                Point(1.0, -1.0),
-            ],
-        };
+        let correct_result = Tsp::new(vec![
+            Point(0.0, 0.0),
+            Point(0.0, 2.0),
+            Point(1.0, 3.0),
+            Point(2.0, 2.0),
+            Point(2.0, 0.0),
+            Point(1.0, -1.0),
+        ]);
 
         let error = (final_state.energy() - correct_result.energy()).abs();
 
@@ -120,31 +365,17 @@ mod tests {
 
     #[test]
     fn test_big_polygon1() {
-        let n_vertices = 20;
-
-        let z =
-            num::complex::Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / n_vertices as f64);
-
-        let state: Vec<_> = (0..n_vertices)
-            .map(|i| z.powi(i))
-            .map(|z| Point(z.re, z.im))
-            .collect();
-
-        let mut tsp = Tsp { state };
-
-        let best_energy = tsp.energy();
-
-        let mut rng = thread_rng();
-        tsp.state.shuffle(&mut rng);
+        let (tsp, best_energy) = shuffled_polygon(20);
 
         let energy_after_shuffle = tsp.energy();
         println!("energy after shuffling: {energy_after_shuffle}");
 
         let sa = SimulatedAnnealing::builder()
             .with_temperature_and_max_iter(|k| 1.0 - 0.001 * k as f64, 3000)
+            .with_seed(0)
             .build();
 
-        let final_state = sa.run(&tsp);
+        let final_state = sa.run(&tsp).best_state;
 
         let error = (best_energy - final_state.energy()).abs();
 
@@ -157,4 +388,97 @@ mod tests {
             )
         }
     }
+
+    #[test]
+    fn test_same_seed_same_tour() {
+        let tsp = Tsp::new(grid());
+
+        let sa = SimulatedAnnealing::builder().with_seed(42).build();
+
+        let first = sa.run(&tsp);
+        let second = sa.run(&tsp);
+
+        assert_eq!(first.best_state.state, second.best_state.state);
+        assert_eq!(first.final_state.state, second.final_state.state);
+    }
+
+    #[test]
+    fn test_report_keeps_best_state() {
+        let tsp = Tsp::new(grid());
+
+        let report = SimulatedAnnealing::builder()
+            .with_temperature_and_max_iter(|_| 100.0, 200)
+            .with_seed(1)
+            .build()
+            .run(&tsp);
+
+        assert!(report.best_energy <= report.final_energy);
+        assert!(report.best_energy <= tsp.energy());
+        assert!((report.best_state.energy() - report.best_energy).abs() < 1e-6);
+        assert_eq!(report.iterations, 200);
+        assert_eq!(report.accepted, 200);
+        assert!(report.evaluations >= report.accepted);
+        assert_eq!(report.stop_reason, StopReason::MaxIterations);
+    }
+
+    #[test]
+    fn test_calibrated_temperature_follows_scale() {
+        let state = grid();
+
+        let scaled: Vec<_> = state
+            .iter()
+            .map(|p| Point(p.0 * 1000.0, p.1 * 1000.0))
+            .collect();
+
+        let sa = SimulatedAnnealing::builder()
+            .with_calibrated_initial_temperature(0.8, 500)
+            .with_max_iter(10)
+            .with_seed(3)
+            .build();
+
+        let t0 = sa.run(&Tsp::new(state)).initial_temperature;
+        let t0_scaled = sa.run(&Tsp::new(scaled)).initial_temperature;
+
+        assert!((t0_scaled / t0 - 1000.0).abs() < 1e-6 * 1000.0);
+    }
+
+    #[test]
+    fn test_two_opt() {
+        let (tsp, best_energy) = shuffled_polygon(30);
+        let tsp = tsp.with_neighborhood(Neighborhood::TwoOpt);
+
+        let report = SimulatedAnnealing::builder()
+            .with_sampling(Sampling::Metropolis)
+            .with_schedule(Geometric::new(0.95))
+            .with_steps_per_temperature(StepsPerTemperature::Proportional(1.0))
+            .with_max_iter(200)
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        assert!((report.best_energy - best_energy).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_coordinates() {
+        let tsp = Tsp::new(vec![Point(0.0, 1.0), Point(2.0, 3.0), Point(4.0, 5.0)]);
+
+        let coordinates = Coordinates::from(&tsp);
+
+        assert_eq!(coordinates.x, vec![0.0, 2.0, 4.0]);
+        assert_eq!(coordinates.y, vec![1.0, 3.0, 5.0]);
+        assert_eq!(
+            serde_json::to_string(&coordinates).unwrap(),
+            r#"{"x":[0.0,2.0,4.0],"y":[1.0,3.0,5.0]}"#
+        );
+
+        let back = Tsp::try_from(coordinates).unwrap();
+        assert_eq!(back.points(), tsp.points());
+
+        let uneven = Coordinates {
+            x: vec![0.0, 1.0],
+            y: vec![0.0],
+        };
+        assert!(Tsp::try_from(uneven).is_err());
+    }
 }
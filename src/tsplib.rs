@@ -0,0 +1,672 @@
+use std::{fs, io::Write, path::Path, str::Lines};
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    matrix::DistanceMatrix,
+    metric::Metric,
+    tsp::{Point, Tsp},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemType {
+    Tsp,
+    /// Asymmetric TSP, where the weight from `i` to `j` may differ from the one from `j`
+    /// to `i`.
+    Atsp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeWeightType {
+    Euc2d,
+    Ceil2d,
+    Geo,
+    Att,
+    Explicit,
+}
+
+impl EdgeWeightType {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        Ok(match value {
+            "EUC_2D" => EdgeWeightType::Euc2d,
+            "CEIL_2D" => EdgeWeightType::Ceil2d,
+            "GEO" => EdgeWeightType::Geo,
+            "ATT" => EdgeWeightType::Att,
+            "EXPLICIT" => EdgeWeightType::Explicit,
+            _ => bail!("unsupported EDGE_WEIGHT_TYPE {value}"),
+        })
+    }
+
+    /// Metric computing the weights from coordinates, `None` for explicit weights.
+    pub fn metric(self) -> Option<Metric> {
+        match self {
+            EdgeWeightType::Euc2d => Some(Metric::RoundedEuclidean),
+            EdgeWeightType::Ceil2d => Some(Metric::CeilEuclidean),
+            EdgeWeightType::Geo => Some(Metric::Geo),
+            EdgeWeightType::Att => Some(Metric::Att),
+            EdgeWeightType::Explicit => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EdgeWeightFormat {
+    FullMatrix,
+    UpperRow,
+    LowerDiagRow,
+}
+
+impl EdgeWeightFormat {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        Ok(match value {
+            "FULL_MATRIX" => EdgeWeightFormat::FullMatrix,
+            "UPPER_ROW" => EdgeWeightFormat::UpperRow,
+            "LOWER_DIAG_ROW" => EdgeWeightFormat::LowerDiagRow,
+            _ => bail!("unsupported EDGE_WEIGHT_FORMAT {value}"),
+        })
+    }
+
+    /// Entries `(i, j)` of the matrix in the order they appear in the file.
+    fn entries(self, n: usize) -> Vec<(usize, usize)> {
+        match self {
+            EdgeWeightFormat::FullMatrix => {
+                (0..n).flat_map(|i| (0..n).map(move |j| (i, j))).collect()
+            }
+            EdgeWeightFormat::UpperRow => (0..n)
+                .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                .collect(),
+            EdgeWeightFormat::LowerDiagRow => {
+                (0..n).flat_map(|i| (0..=i).map(move |j| (i, j))).collect()
+            }
+        }
+    }
+}
+
+/// A TSP or ATSP instance in TSPLIB format.
+///
+/// Distances follow the TSPLIB conventions, so [`Instance::tour_length`] can be compared
+/// with published optima.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub name: String,
+    pub comment: Option<String>,
+    pub problem_type: ProblemType,
+    pub dimension: usize,
+    pub edge_weight_type: EdgeWeightType,
+    /// Node coordinates, empty for explicit instances without them.
+    pub coordinates: Vec<Point>,
+    weights: Option<DistanceMatrix>,
+}
+
+impl Instance {
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let input = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+
+        Self::parse(&input).with_context(|| format!("invalid TSPLIB file {}", path.display()))
+    }
+
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut name = String::new();
+        let mut comment: Option<String> = None;
+        let mut problem_type = ProblemType::Tsp;
+        let mut dimension = None;
+        let mut edge_weight_type = None;
+        let mut edge_weight_format = None;
+        let mut coordinates = Vec::new();
+        let mut weights = None;
+
+        let mut lines = input.lines();
+
+        while let Some(line) = lines.next() {
+            let (key, value) = split_entry(line);
+
+            match key {
+                "" => {}
+                "EOF" => break,
+                "NAME" => name = value.to_string(),
+                "COMMENT" => match &mut comment {
+                    Some(comment) => {
+                        comment.push('\n');
+                        comment.push_str(value);
+                    }
+                    None => comment = Some(value.to_string()),
+                },
+                "TYPE" => {
+                    problem_type = match value {
+                        "TSP" => ProblemType::Tsp,
+                        "ATSP" => ProblemType::Atsp,
+                        _ => bail!("unsupported TYPE {value}"),
+                    }
+                }
+                "DIMENSION" => {
+                    dimension = Some(value.parse().context("invalid DIMENSION")?);
+                }
+                "EDGE_WEIGHT_TYPE" => edge_weight_type = Some(EdgeWeightType::parse(value)?),
+                "EDGE_WEIGHT_FORMAT" => edge_weight_format = Some(EdgeWeightFormat::parse(value)?),
+                "NODE_COORD_TYPE" | "DISPLAY_DATA_TYPE" => {}
+                "NODE_COORD_SECTION" | "DISPLAY_DATA_SECTION" => {
+                    let n = dimension.context("DIMENSION must come before the sections")?;
+                    let numbers = numbers(&mut lines, 3 * n).with_context(|| key.to_string())?;
+
+                    let points = node_coordinates(&numbers, n).with_context(|| key.to_string())?;
+
+                    if key == "NODE_COORD_SECTION" || coordinates.is_empty() {
+                        coordinates = points;
+                    }
+                }
+                "EDGE_WEIGHT_SECTION" => {
+                    let n = dimension.context("DIMENSION must come before the sections")?;
+                    let format = edge_weight_format.context("missing EDGE_WEIGHT_FORMAT")?;
+
+                    let entries = format.entries(n);
+                    let numbers = numbers(&mut lines, entries.len()).context(key.to_string())?;
+
+                    let mut matrix = vec![0.0; n * n];
+
+                    for ((i, j), weight) in entries.into_iter().zip(numbers) {
+                        matrix[i * n + j] = weight;
+
+                        if format != EdgeWeightFormat::FullMatrix {
+                            matrix[j * n + i] = weight;
+                        }
+                    }
+
+                    weights = Some(DistanceMatrix::new(n, matrix)?);
+                }
+                _ => bail!("unsupported entry {key}"),
+            }
+        }
+
+        let dimension = dimension.context("missing DIMENSION")?;
+        let edge_weight_type = edge_weight_type.context("missing EDGE_WEIGHT_TYPE")?;
+
+        match edge_weight_type {
+            EdgeWeightType::Explicit => ensure!(weights.is_some(), "missing EDGE_WEIGHT_SECTION"),
+            _ => ensure!(!coordinates.is_empty(), "missing NODE_COORD_SECTION"),
+        }
+
+        if problem_type == ProblemType::Atsp {
+            ensure!(
+                edge_weight_format == Some(EdgeWeightFormat::FullMatrix),
+                "ATSP instances need an EXPLICIT FULL_MATRIX"
+            );
+        }
+
+        Ok(Instance {
+            name,
+            comment,
+            problem_type,
+            dimension,
+            edge_weight_type,
+            coordinates,
+            weights,
+        })
+    }
+
+    /// Distance between nodes `i` and `j`, counted from zero.
+    pub fn distance(&self, i: usize, j: usize) -> f64 {
+        if let Some(weights) = &self.weights {
+            return weights.get(i, j);
+        }
+
+        let metric = self
+            .edge_weight_type
+            .metric()
+            .expect("explicit instances have weights");
+
+        metric.distance(&self.coordinates[i], &self.coordinates[j])
+    }
+
+    pub fn tour_length(&self, tour: &Tour) -> f64 {
+        let nodes = &tour.nodes;
+
+        nodes
+            .iter()
+            .zip(nodes.iter().cycle().skip(1))
+            .map(|(&i, &j)| self.distance(i, j))
+            .sum()
+    }
+
+    /// Distances between all pairs of nodes, for a [`MatrixTsp`](crate::matrix::MatrixTsp)
+    /// or an [`Atsp`](crate::atsp::Atsp).
+    pub fn distance_matrix(&self) -> DistanceMatrix {
+        match &self.weights {
+            Some(weights) => weights.clone(),
+            None => DistanceMatrix::from_fn(self.dimension, |i, j| self.distance(i, j)),
+        }
+    }
+
+    /// [`Tsp`] over the node coordinates, in file order, measured with the metric of
+    /// the instance.
+    pub fn to_tsp(&self) -> anyhow::Result<Tsp> {
+        let metric = self
+            .edge_weight_type
+            .metric()
+            .with_context(|| format!("instance {} has explicit weights", self.name))?;
+
+        Ok(Tsp::new(self.coordinates.clone()).with_metric(metric))
+    }
+
+    /// Tour visiting the nodes in the order of `points`, typically the best state of a
+    /// run started from [`Instance::to_tsp`].
+    pub fn tour_of(&self, points: &[Point]) -> anyhow::Result<Tour> {
+        ensure!(
+            points.len() == self.dimension,
+            "expected {} points, got {}",
+            self.dimension,
+            points.len()
+        );
+
+        let mut used = vec![false; self.dimension];
+
+        let nodes = points
+            .iter()
+            .map(|point| {
+                let node = (0..self.dimension)
+                    .find(|&node| !used[node] && self.coordinates[node] == *point)
+                    .with_context(|| format!("{point:?} is not a node of {}", self.name))?;
+
+                used[node] = true;
+
+                Ok(node)
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Tour {
+            name: self.name.clone(),
+            nodes,
+        })
+    }
+}
+
+/// A tour in TSPLIB `.tour` format, with nodes counted from zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tour {
+    pub name: String,
+    pub nodes: Vec<usize>,
+}
+
+impl Tour {
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let input = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+
+        Self::parse(&input).with_context(|| format!("invalid tour file {}", path.display()))
+    }
+
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut name = String::new();
+        let mut dimension: Option<usize> = None;
+        let mut nodes = None;
+
+        let mut lines = input.lines();
+
+        while let Some(line) = lines.next() {
+            let (key, value) = split_entry(line);
+
+            match key {
+                "" | "COMMENT" => {}
+                "EOF" => break,
+                "NAME" => name = value.to_string(),
+                "TYPE" => ensure!(value == "TOUR", "unsupported TYPE {value}"),
+                "DIMENSION" => {
+                    dimension = Some(value.parse().context("invalid DIMENSION")?);
+                }
+                "TOUR_SECTION" => {
+                    let mut section = Vec::new();
+
+                    'section: for line in lines.by_ref() {
+                        for token in line.split_whitespace() {
+                            let node: i64 = token
+                                .parse()
+                                .with_context(|| format!("invalid node {token}"))?;
+
+                            if node == -1 {
+                                break 'section;
+                            }
+
+                            ensure!(node >= 1, "invalid node {node}");
+                            section.push(node as usize - 1);
+                        }
+                    }
+
+                    nodes = Some(section);
+                }
+                _ => bail!("unsupported entry {key}"),
+            }
+        }
+
+        let nodes = nodes.context("missing TOUR_SECTION")?;
+        let n = dimension.unwrap_or(nodes.len());
+
+        ensure!(
+            nodes.len() == n,
+            "DIMENSION is {n} but the tour has {} nodes",
+            nodes.len()
+        );
+
+        let mut seen = vec![false; n];
+
+        for &node in &nodes {
+            ensure!(
+                node < n && !seen[node],
+                "node {} is not in 1..={n} or repeated",
+                node + 1
+            );
+            seen[node] = true;
+        }
+
+        Ok(Tour { name, nodes })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> anyhow::Result<()> {
+        writeln!(writer, "NAME : {}", self.name)?;
+        writeln!(writer, "TYPE : TOUR")?;
+        writeln!(writer, "DIMENSION : {}", self.nodes.len())?;
+        writeln!(writer, "TOUR_SECTION")?;
+
+        for node in &self.nodes {
+            writeln!(writer, "{}", node + 1)?;
+        }
+
+        writeln!(writer, "-1")?;
+        writeln!(writer, "EOF")?;
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+
+        let file = fs::File::create(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+
+        self.write(std::io::BufWriter::new(file))
+    }
+}
+
+/// Splits `KEY : VALUE` lines. Section headers have no value.
+fn split_entry(line: &str) -> (&str, &str) {
+    match line.split_once(':') {
+        Some((key, value)) => (key.trim(), value.trim()),
+        None => (line.trim(), ""),
+    }
+}
+
+/// Places the `id x y` triples of a coordinate section by node id, which must cover
+/// `1..=n` exactly once, in any order.
+fn node_coordinates(numbers: &[f64], n: usize) -> anyhow::Result<Vec<Point>> {
+    let mut points = vec![None; n];
+
+    for node in numbers.chunks(3) {
+        let id = node[0];
+
+        ensure!(
+            id.fract() == 0.0 && 1.0 <= id && id <= n as f64,
+            "node id {id} is not in 1..={n}"
+        );
+
+        let point = &mut points[id as usize - 1];
+
+        ensure!(point.is_none(), "node {id} is listed twice");
+        *point = Some(Point(node[1], node[2]));
+    }
+
+    points
+        .into_iter()
+        .enumerate()
+        .map(|(i, point)| point.with_context(|| format!("node {} is missing", i + 1)))
+        .collect()
+}
+
+/// Reads the next `count` numbers, which may be spread over several lines.
+fn numbers(lines: &mut Lines, count: usize) -> anyhow::Result<Vec<f64>> {
+    let mut numbers = Vec::with_capacity(count);
+
+    while numbers.len() < count {
+        let line = lines
+            .next()
+            .with_context(|| format!("expected {count} numbers, got {}", numbers.len()))?;
+
+        for token in line.split_whitespace() {
+            numbers.push(
+                token
+                    .parse()
+                    .with_context(|| format!("invalid number {token}"))?,
+            );
+        }
+    }
+
+    ensure!(
+        numbers.len() == count,
+        "expected {count} numbers, got {}",
+        numbers.len()
+    );
+
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        atsp::Atsp,
+        matrix::MatrixTsp,
+        simulated_annealing::{SimulatedAnnealing, State},
+        tsp::Neighborhood,
+    };
+
+    use super::*;
+
+    const SQUARE: &str = "NAME : square5
+COMMENT : A square with a point in the middle
+TYPE : TSP
+DIMENSION : 5
+EDGE_WEIGHT_TYPE : EUC_2D
+NODE_COORD_SECTION
+1 0 0
+2 10 10
+3 0 10
+4 10 0
+5 5 5.5
+EOF
+";
+
+    #[test]
+    fn test_coordinates() {
+        let instance = Instance::parse(SQUARE).unwrap();
+
+        assert_eq!(instance.name, "square5");
+        assert_eq!(instance.dimension, 5);
+        assert_eq!(instance.coordinates[4], Point(5.0, 5.5));
+
+        assert_eq!(instance.distance(0, 1), 14.0);
+        assert_eq!(instance.distance(0, 4), 7.0);
+
+        let tour = Tour {
+            name: "square5".to_string(),
+            nodes: vec![0, 2, 1, 3, 4],
+        };
+
+        assert_eq!(instance.tour_length(&tour), 10.0 + 10.0 + 10.0 + 7.0 + 7.0);
+    }
+
+    #[test]
+    fn test_nodes_out_of_order() {
+        let shuffled = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION
+3 0 7\n1 0 0\n2 3 4\nEOF\n";
+
+        let instance = Instance::parse(shuffled).unwrap();
+
+        assert_eq!(
+            instance.coordinates,
+            vec![Point(0.0, 0.0), Point(3.0, 4.0), Point(0.0, 7.0)]
+        );
+        assert_eq!(instance.distance(0, 1), 5.0);
+
+        let repeated = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION
+1 0 0\n2 3 4\n2 0 7\n";
+        let out_of_range = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION
+1 0 0\n2 3 4\n4 0 7\n";
+        let fractional = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION
+1 0 0\n2 3 4\n2.5 0 7\n";
+
+        for input in [repeated, out_of_range, fractional] {
+            assert!(Instance::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let instance = |kind: &str, a: (f64, f64), b: (f64, f64)| {
+            let input = format!(
+                "DIMENSION: 2\nEDGE_WEIGHT_TYPE: {kind}\nNODE_COORD_SECTION\n1 {} {}\n2 {} {}\n",
+                a.0, a.1, b.0, b.1
+            );
+
+            Instance::parse(&input).unwrap().distance(0, 1)
+        };
+
+        assert_eq!(instance("EUC_2D", (0.0, 0.0), (1.0, 1.0)), 1.0);
+        assert_eq!(instance("CEIL_2D", (0.0, 0.0), (1.0, 1.0)), 2.0);
+        assert_eq!(instance("ATT", (0.0, 0.0), (30.0, 40.0)), 16.0);
+        assert_eq!(instance("ATT", (0.0, 0.0), (0.0, 10.0)), 4.0);
+        assert_eq!(instance("GEO", (0.0, 0.0), (0.0, 1.0)), 112.0);
+        assert_eq!(instance("GEO", (0.0, 0.0), (0.0, 0.3)), 56.0);
+    }
+
+    #[test]
+    fn test_explicit() {
+        let full = "TYPE: TSP\nDIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: FULL_MATRIX\nEDGE_WEIGHT_SECTION\n0 1 2\n1 0 3\n2 3 0\nEOF\n";
+        let upper = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_ROW\nEDGE_WEIGHT_SECTION\n1 2\n3\n";
+        let lower = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: LOWER_DIAG_ROW\nEDGE_WEIGHT_SECTION\n0 1 0 2 3 0\n";
+
+        for input in [full, upper, lower] {
+            let instance = Instance::parse(input).unwrap();
+
+            assert_eq!(instance.distance(0, 1), 1.0);
+            assert_eq!(instance.distance(2, 0), 2.0);
+            assert_eq!(instance.distance(1, 2), 3.0);
+            assert!(instance.to_tsp().is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_instances() {
+        let missing_section = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nEOF\n";
+        let short_section = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n";
+        let unknown_metric = "DIMENSION: 1\nEDGE_WEIGHT_TYPE: XRAY1\n";
+
+        for input in [missing_section, short_section, unknown_metric] {
+            assert!(Instance::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_tour() {
+        let tour = Tour {
+            name: "square5".to_string(),
+            nodes: vec![0, 2, 1, 3, 4],
+        };
+
+        let mut buffer = Vec::new();
+        tour.write(&mut buffer).unwrap();
+
+        let written = String::from_utf8(buffer).unwrap();
+
+        assert!(written.contains("TOUR_SECTION\n1\n3\n2\n4\n5\n-1\nEOF"));
+        assert_eq!(Tour::parse(&written).unwrap(), tour);
+
+        assert!(Tour::parse("TOUR_SECTION\n1 2 2\n-1\n").is_err());
+        assert!(Tour::parse("DIMENSION: 3\nTOUR_SECTION\n1 2\n-1\n").is_err());
+    }
+
+    #[test]
+    fn test_solve() {
+        let instance = Instance::parse(SQUARE).unwrap();
+
+        let tsp = instance
+            .to_tsp()
+            .unwrap()
+            .with_neighborhood(Neighborhood::TwoOpt);
+
+        let report = SimulatedAnnealing::builder()
+            .with_max_iter(200)
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        let tour = instance.tour_of(report.best_state.points()).unwrap();
+
+        assert_eq!(instance.tour_length(&tour), 44.0);
+    }
+
+    #[test]
+    fn test_solve_explicit() {
+        let input = "NAME: triangle4\nTYPE: TSP\nDIMENSION: 4\nEDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_ROW\nEDGE_WEIGHT_SECTION\n1 5 2\n1 5\n1\nEOF\n";
+
+        let instance = Instance::parse(input).unwrap();
+        let tsp = MatrixTsp::new(Arc::new(instance.distance_matrix())).unwrap();
+
+        let report = SimulatedAnnealing::builder()
+            .with_max_iter(100)
+            .with_seed(0)
+            .build()
+            .run(&tsp);
+
+        let tour = Tour {
+            name: instance.name.clone(),
+            nodes: report.best_state.tour().to_vec(),
+        };
+
+        assert_eq!(report.best_energy, 5.0);
+        assert_eq!(instance.tour_length(&tour), report.best_state.energy());
+    }
+
+    #[test]
+    fn test_atsp() {
+        let input = "NAME: one_way4\nTYPE: ATSP\nDIMENSION: 4\nEDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: FULL_MATRIX\nEDGE_WEIGHT_SECTION
+0 1 9 9\n9 0 1 9\n9 9 0 1\n1 9 9 0\nEOF\n";
+
+        let instance = Instance::parse(input).unwrap();
+
+        assert_eq!(instance.problem_type, ProblemType::Atsp);
+        assert_eq!(instance.distance(0, 1), 1.0);
+        assert_eq!(instance.distance(1, 0), 9.0);
+
+        let atsp = Atsp::new(Arc::new(instance.distance_matrix()))
+            .with_tour(vec![3, 2, 1, 0])
+            .unwrap();
+
+        assert_eq!(atsp.energy(), 36.0);
+
+        let report = SimulatedAnnealing::builder()
+            .with_max_iter(100)
+            .with_seed(0)
+            .build()
+            .run(&atsp);
+
+        let tour = Tour {
+            name: instance.name.clone(),
+            nodes: report.best_state.tour().to_vec(),
+        };
+
+        assert_eq!(report.best_energy, 4.0);
+        assert_eq!(instance.tour_length(&tour), 4.0);
+
+        let triangular = "TYPE: ATSP\nDIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_ROW\nEDGE_WEIGHT_SECTION\n1 2\n3\n";
+        assert!(Instance::parse(triangular).is_err());
+    }
+}